@@ -1,5 +1,8 @@
 use tauri::{Window, Runtime};
 use crate::AppState;
+use crate::engine::hwaccel::{self, DecodePath, HwAccel};
+use crate::engine::time::Time;
+use std::collections::HashMap;
 
 #[tauri::command]
 pub async fn attach_wgpu_renderer<R: Runtime>(
@@ -9,7 +12,15 @@ pub async fn attach_wgpu_renderer<R: Runtime>(
     println!("Requesting WGPU Attachment for Window: {}", window.label());
     
     let mut engine = state.engine.lock().await;
-    
+
+    // Size the surface to the window; `resize` keeps it in sync afterwards
+    if let Ok(size) = window.inner_size() {
+        if size.width > 0 && size.height > 0 {
+            engine.width = size.width;
+            engine.height = size.height;
+        }
+    }
+
     match engine.init_surface(window).await {
         Ok(_) => {
              println!("Backend: WGPU Surface Attached Successfully");
@@ -37,12 +48,39 @@ pub async fn pause(state: tauri::State<'_, AppState>) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub async fn seek(time: f64, state: tauri::State<'_, AppState>) -> Result<(), String> {
+pub async fn seek(time: Time, state: tauri::State<'_, AppState>) -> Result<(), String> {
     let mut engine = state.engine.lock().await;
     engine.seek(time);
     Ok(())
 }
 
+/// Memory budget for decoded preview frames. Shrinking it evicts the oldest frames.
+#[tauri::command]
+pub async fn set_frame_cache_budget(megabytes: u32, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    let mut engine = state.engine.lock().await;
+    engine.frame_cache.set_budget(megabytes as usize * 1024 * 1024);
+    Ok(())
+}
+
+/// Decoding device for clips opened from now on (`auto`, `off` or a device name).
+/// Without the `hwaccel` feature everything decodes in software.
+#[tauri::command]
+pub async fn set_hardware_decoding(mode: HwAccel) -> Result<(), String> {
+    hwaccel::set_preference(mode);
+    Ok(())
+}
+
+/// Hardware device or software per visible clip, keyed by clip ID. Clips still opening are left out.
+#[tauri::command]
+pub async fn get_decode_paths(state: tauri::State<'_, AppState>) -> Result<HashMap<String, DecodePath>, String> {
+    let mut engine = state.engine.lock().await;
+    Ok(engine
+        .layers
+        .iter_mut()
+        .filter_map(|(id, layer)| Some((id.clone(), layer.decode_path()?)))
+        .collect())
+}
+
 #[tauri::command]
 pub async fn get_playback_state(state: tauri::State<'_, AppState>) -> Result<crate::engine::PlaybackState, String> {
     let engine = state.engine.lock().await;
@@ -1,5 +1,37 @@
-// Export Commands Placeholder
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Runtime};
+
+use crate::engine::export::{ExportRequest, Exporter};
+use crate::engine::export_utils::{ProgressCallback, JOB_PROGRESS_EVENT};
+use crate::AppState;
+
+/// Forwards job progress to the frontend as `job-progress` events.
+pub fn progress_emitter<R: Runtime>(app: AppHandle<R>) -> ProgressCallback {
+    Arc::new(move |progress| {
+        if let Err(e) = app.emit(JOB_PROGRESS_EVENT, progress) {
+            eprintln!("Failed to emit progress: {}", e);
+        }
+    })
+}
+
 #[tauri::command]
-pub fn export_video() {
-    println!("TODO: Implement Export");
+pub async fn export_video<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    settings: ExportRequest,
+) -> Result<String, String> {
+    // Snapshot the timeline so editing and playback continue during export
+    let timeline = {
+        let engine = state.engine.lock().await;
+        engine.timeline_manager.timeline.clone()
+    };
+
+    let exporter = Exporter::new(timeline, settings)
+        .map_err(|e| e.to_string())?
+        .with_progress(progress_emitter(app));
+
+    tauri::async_runtime::spawn_blocking(move || exporter.run())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
 }
@@ -1,13 +1,21 @@
-use tauri::State;
+use tauri::{AppHandle, Runtime, State};
 use crate::AppState;
+use super::export::progress_emitter;
 
 #[tauri::command]
-pub async fn load_file(path: String, state: State<'_, AppState>) -> Result<String, String> {
+pub async fn load_file<R: Runtime>(
+    app: AppHandle<R>,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
     let path = path.trim_matches('"').to_string(); // Remove copy-paste quotes
     println!("Backend: Loading File: {}", path);
     // Request proxy generation (async)
     // Returns original path if proxy not ready, or proxy path if ready/exists
-    let effective_path = state.proxy_manager.ensure_proxy(path.clone()).await;
+    let effective_path = state
+        .proxy_manager
+        .ensure_proxy(path.clone(), Some(progress_emitter(app)))
+        .await;
     
     // For now, we just return the path to Frontend, which might call another command or we trust Frontend to handle it.
     // If the frontend loads the video into the Viewport, it calls `attach_wgpu_renderer`.
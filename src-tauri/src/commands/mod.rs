@@ -1,4 +1,5 @@
 pub mod engine;
 pub mod export;
 pub mod library;
+pub mod project;
 pub mod timeline;
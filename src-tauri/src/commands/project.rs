@@ -0,0 +1,59 @@
+use std::path::Path;
+
+use crate::engine::project;
+use crate::engine::settings::ProjectSettings;
+use crate::engine::time::Time;
+use crate::engine::timeline::Timeline;
+use crate::AppState;
+
+#[tauri::command]
+pub async fn save_project(state: tauri::State<'_, AppState>, path: String) -> Result<(), String> {
+    // Snapshot under the lock, write to disk without it
+    let timeline = {
+        let engine = state.engine.lock().await;
+        engine.timeline_manager.timeline.clone()
+    };
+
+    project::save_project(Path::new(&path), &timeline).map_err(|e| e.to_string())
+}
+
+/// Loads a project into the engine and returns it so the frontend can rebuild its stores.
+#[tauri::command]
+pub async fn open_project(
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<Timeline, String> {
+    let timeline = project::open_project(Path::new(&path)).map_err(|e| e.to_string())?;
+
+    let mut engine = state.engine.lock().await;
+    engine.pause();
+    engine.timeline_manager.load_timeline(timeline);
+    engine.seek(Time::default()); // Rebuilds layers for the new clips
+
+    Ok(engine.timeline_manager.timeline.clone())
+}
+
+#[tauri::command]
+pub async fn get_project_settings(
+    state: tauri::State<'_, AppState>,
+) -> Result<ProjectSettings, String> {
+    let engine = state.engine.lock().await;
+    Ok(*engine.timeline_manager.settings())
+}
+
+/// Changes canvas size, frame rate, background... (undoable). Returns the timeline,
+/// since a new frame rate moves clips onto the new frame grid.
+#[tauri::command]
+pub async fn set_project_settings(
+    state: tauri::State<'_, AppState>,
+    settings: ProjectSettings,
+) -> Result<Timeline, String> {
+    let mut engine = state.engine.lock().await;
+    engine.timeline_manager.set_settings(settings)?;
+
+    // Puts the playhead on the new frame grid and redraws
+    let time = engine.playback_state.current_time;
+    engine.seek(time);
+
+    Ok(engine.timeline_manager.timeline.clone())
+}
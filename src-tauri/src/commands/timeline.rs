@@ -1,14 +1,35 @@
 use crate::AppState;
-use crate::engine::timeline::Clip;
+use crate::engine::export_utils::probe_length;
+use crate::engine::framing::ScaleMode;
+use crate::engine::keyframes::Keyframe;
+use crate::engine::placement::{PlacementError, PlacementPolicy, PlacementReport};
+use crate::engine::snapping::{SnapOptions, SnapResult, SnapTargets};
+use crate::engine::time::Time;
+use crate::engine::timeline::{Clip, Timeline, Track, TrackKind};
 
+/// Adds a clip. `policy` decides what happens to clips already there
+/// (defaults to rejecting overlaps); the report lists every clip that changed.
 #[tauri::command]
 pub async fn add_clip(
     state: tauri::State<'_, AppState>,
-    clip: Clip,
-) -> Result<(), String> {
+    mut clip: Clip,
+    policy: Option<PlacementPolicy>,
+) -> Result<PlacementReport, PlacementError> {
+    // Source length bounds trims and slips; probe (a blocking file open)
+    // off the async runtime and before taking the lock
+    if clip.source_duration.is_none() {
+        let path = clip.path.clone();
+        clip.source_duration = tauri::async_runtime::spawn_blocking(move || probe_length(&path))
+            .await
+            .map_err(|e| PlacementError::Invalid {
+                message: e.to_string(),
+            })?;
+    }
+
     let mut engine = state.engine.lock().await;
-    engine.timeline_manager.add_clip(clip);
-    Ok(())
+    engine
+        .timeline_manager
+        .place_clip(clip, policy.unwrap_or_default())
 }
 
 #[tauri::command]
@@ -17,8 +38,7 @@ pub async fn remove_clip(
     id: String,
 ) -> Result<(), String> {
     let mut engine = state.engine.lock().await;
-    engine.timeline_manager.remove_clip(&id);
-    Ok(())
+    engine.timeline_manager.remove_clip(&id)
 }
 
 // Legacy/Bulk Sync (Optional, but good for "Load Project")
@@ -29,12 +49,277 @@ pub async fn update_composition(
 ) -> Result<(), String> {
     let mut engine = state.engine.lock().await;
     
-    // Naively clear and re-add
+    // Naively clear and re-add (recorded as one undo step)
     // Real implementation should Diff, but this is safe for now
-    engine.timeline_manager.timeline.clips.clear();
-    for clip in new_clips {
-        engine.timeline_manager.add_clip(clip);
-    }
-    
+    engine.timeline_manager.replace_clips(new_clips)
+}
+
+#[tauri::command]
+pub async fn update_clip(
+    state: tauri::State<'_, AppState>,
+    clip: Clip,
+) -> Result<(), String> {
+    let mut engine = state.engine.lock().await;
+    engine.timeline_manager.update_clip(clip)
+}
+
+#[tauri::command]
+pub async fn set_keyframe(
+    state: tauri::State<'_, AppState>,
+    clip_id: String,
+    property: String,
+    keyframe: Keyframe,
+) -> Result<(), String> {
+    let mut engine = state.engine.lock().await;
+    engine.timeline_manager.set_keyframe(&clip_id, &property, keyframe)
+}
+
+#[tauri::command]
+pub async fn remove_keyframe(
+    state: tauri::State<'_, AppState>,
+    clip_id: String,
+    property: String,
+    time: Time,
+) -> Result<(), String> {
+    let mut engine = state.engine.lock().await;
+    engine.timeline_manager.remove_keyframe(&clip_id, &property, time)
+}
+
+#[tauri::command]
+pub async fn set_clip_framing(
+    state: tauri::State<'_, AppState>,
+    clip_id: String,
+    scale_mode: ScaleMode,
+    reframe: [f32; 2],
+) -> Result<(), String> {
+    let mut engine = state.engine.lock().await;
+    engine.timeline_manager.set_clip_framing(&clip_id, scale_mode, reframe)
+}
+
+// --- Editing ---
+
+/// Returns the ID of the new right-hand clip.
+#[tauri::command]
+pub async fn split_clip(
+    state: tauri::State<'_, AppState>,
+    id: String,
+    time: Time,
+) -> Result<String, String> {
+    let mut engine = state.engine.lock().await;
+    engine.timeline_manager.split_clip(&id, time)
+}
+
+#[tauri::command]
+pub async fn trim_clip_in(
+    state: tauri::State<'_, AppState>,
+    id: String,
+    time: Time,
+) -> Result<(), String> {
+    let mut engine = state.engine.lock().await;
+    engine.timeline_manager.trim_in(&id, time)
+}
+
+#[tauri::command]
+pub async fn trim_clip_out(
+    state: tauri::State<'_, AppState>,
+    id: String,
+    time: Time,
+) -> Result<(), String> {
+    let mut engine = state.engine.lock().await;
+    engine.timeline_manager.trim_out(&id, time)
+}
+
+#[tauri::command]
+pub async fn ripple_delete(state: tauri::State<'_, AppState>, id: String) -> Result<(), String> {
+    let mut engine = state.engine.lock().await;
+    engine.timeline_manager.ripple_delete(&id)
+}
+
+#[tauri::command]
+pub async fn slip_clip(
+    state: tauri::State<'_, AppState>,
+    id: String,
+    delta: Time,
+) -> Result<(), String> {
+    let mut engine = state.engine.lock().await;
+    engine.timeline_manager.slip(&id, delta)
+}
+
+#[tauri::command]
+pub async fn slide_clip(
+    state: tauri::State<'_, AppState>,
+    id: String,
+    delta: Time,
+) -> Result<(), String> {
+    let mut engine = state.engine.lock().await;
+    engine.timeline_manager.slide(&id, delta)
+}
+
+/// Connects a clip to an anchor clip (it then moves with it); `None` disconnects.
+#[tauri::command]
+pub async fn connect_clip(
+    state: tauri::State<'_, AppState>,
+    id: String,
+    anchor_id: Option<String>,
+) -> Result<(), String> {
+    let mut engine = state.engine.lock().await;
+    engine
+        .timeline_manager
+        .connect_clip(&id, anchor_id.as_deref())
+}
+
+// --- Tracks ---
+
+#[tauri::command]
+pub async fn add_track(
+    state: tauri::State<'_, AppState>,
+    name: String,
+    kind: TrackKind,
+) -> Result<Track, String> {
+    let mut engine = state.engine.lock().await;
+    Ok(engine.timeline_manager.add_track(&name, kind))
+}
+
+#[tauri::command]
+pub async fn remove_track(state: tauri::State<'_, AppState>, id: i32) -> Result<(), String> {
+    let mut engine = state.engine.lock().await;
+    engine.timeline_manager.remove_track(id)
+}
+
+#[tauri::command]
+pub async fn rename_track(
+    state: tauri::State<'_, AppState>,
+    id: i32,
+    name: String,
+) -> Result<(), String> {
+    let mut engine = state.engine.lock().await;
+    engine.timeline_manager.rename_track(id, &name)
+}
+
+/// `track_ids` lists every track, bottom to top.
+#[tauri::command]
+pub async fn reorder_tracks(
+    state: tauri::State<'_, AppState>,
+    track_ids: Vec<i32>,
+) -> Result<(), String> {
+    let mut engine = state.engine.lock().await;
+    engine.timeline_manager.reorder_tracks(&track_ids)
+}
+
+#[tauri::command]
+pub async fn set_track_locked(
+    state: tauri::State<'_, AppState>,
+    id: i32,
+    locked: bool,
+) -> Result<(), String> {
+    let mut engine = state.engine.lock().await;
+    engine.timeline_manager.set_track_locked(id, locked)
+}
+
+#[tauri::command]
+pub async fn set_track_muted(
+    state: tauri::State<'_, AppState>,
+    id: i32,
+    muted: bool,
+) -> Result<(), String> {
+    let mut engine = state.engine.lock().await;
+    engine.timeline_manager.set_track_muted(id, muted)
+}
+
+#[tauri::command]
+pub async fn set_track_solo(
+    state: tauri::State<'_, AppState>,
+    id: i32,
+    solo: bool,
+) -> Result<(), String> {
+    let mut engine = state.engine.lock().await;
+    engine.timeline_manager.set_track_solo(id, solo)
+}
+
+#[tauri::command]
+pub async fn set_track_magnetic(
+    state: tauri::State<'_, AppState>,
+    id: i32,
+    magnetic: bool,
+) -> Result<(), String> {
+    let mut engine = state.engine.lock().await;
+    engine.timeline_manager.set_track_magnetic(id, magnetic)
+}
+
+// --- Markers & Snapping ---
+
+/// Returns the new marker's ID.
+#[tauri::command]
+pub async fn add_marker(
+    state: tauri::State<'_, AppState>,
+    time: Time,
+    name: String,
+) -> Result<String, String> {
+    let mut engine = state.engine.lock().await;
+    engine.timeline_manager.add_marker(time, &name)
+}
+
+#[tauri::command]
+pub async fn remove_marker(state: tauri::State<'_, AppState>, id: String) -> Result<(), String> {
+    let mut engine = state.engine.lock().await;
+    engine.timeline_manager.remove_marker(&id)
+}
+
+/// Snaps a dragged time so the frontend uses the engine's targets and frame grid.
+/// `threshold` is in seconds (pixels / zoom on the frontend side).
+#[tauri::command]
+pub async fn snap_time(
+    state: tauri::State<'_, AppState>,
+    candidate: Time,
+    threshold: Time,
+    options: Option<SnapOptions>,
+) -> Result<SnapResult, String> {
+    let engine = state.engine.lock().await;
+    let targets = SnapTargets {
+        options: options.unwrap_or_default(),
+        playhead: engine.playback_state.current_time,
+    };
+    Ok(engine.timeline_manager.snap_time(candidate, threshold, &targets))
+}
+
+// --- History ---
+
+/// Groups the following edits into one undo step, e.g. on drag start.
+#[tauri::command]
+pub async fn begin_edit_group(
+    state: tauri::State<'_, AppState>,
+    label: String,
+) -> Result<(), String> {
+    let mut engine = state.engine.lock().await;
+    engine.timeline_manager.begin_edit_group(&label);
     Ok(())
 }
+
+#[tauri::command]
+pub async fn end_edit_group(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    let mut engine = state.engine.lock().await;
+    engine.timeline_manager.end_edit_group();
+    Ok(())
+}
+
+/// Returns the restored timeline so the frontend can rebuild its stores.
+/// The preview follows on the next engine tick, which re-syncs layers while paused too.
+#[tauri::command]
+pub async fn undo(state: tauri::State<'_, AppState>) -> Result<Timeline, String> {
+    let mut engine = state.engine.lock().await;
+    engine
+        .timeline_manager
+        .undo()
+        .ok_or("Nothing to undo".to_string())?;
+    Ok(engine.timeline_manager.timeline.clone())
+}
+
+#[tauri::command]
+pub async fn redo(state: tauri::State<'_, AppState>) -> Result<Timeline, String> {
+    let mut engine = state.engine.lock().await;
+    engine
+        .timeline_manager
+        .redo()
+        .ok_or("Nothing to redo".to_string())?;
+    Ok(engine.timeline_manager.timeline.clone())
+}
@@ -0,0 +1,717 @@
+use anyhow::Result;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+
+use super::audio_worker::AudioWorker;
+use super::time::{Rational, Time};
+use super::timeline::{Clip, TimelineManager};
+
+pub const OUTPUT_CHANNELS: usize = 2; // Mixer output is interleaved stereo f32
+const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+const MIX_BLOCK_FRAMES: usize = 1024;
+const MIX_LEAD: f64 = 0.1; // Seconds mixed ahead of the audio clock
+const RESYNC_TOLERANCE: f64 = 0.05; // Drift (seconds) before a clip decoder re-seeks
+const PREROLL_WINDOW: f64 = 1.0; // Seconds before a clip starts that its decoder opens
+
+pub struct AudioDecoder {
+    pub file_path: String,
+    context: ffmpeg::format::context::Input,
+    decoder: ffmpeg::decoder::Audio,
+    resampler: ffmpeg::software::resampling::Context,
+    stream_index: usize,
+    time_base: ffmpeg::Rational,
+    eof_sent: bool,
+}
+
+// SAFETY: Same contract as VideoDecoder - owned and used by one AudioWorker thread at a time.
+unsafe impl Send for AudioDecoder {}
+
+impl AudioDecoder {
+    /// Opens the best audio stream and resamples it to stereo f32 at `sample_rate`.
+    pub fn new(path: &str, sample_rate: u32) -> Result<Self> {
+        ffmpeg::init()?;
+
+        let context = ffmpeg::format::input(&Path::new(path))?;
+
+        let stream = context
+            .streams()
+            .best(ffmpeg::media::Type::Audio)
+            .ok_or(anyhow::anyhow!("No audio stream found"))?;
+
+        let stream_index = stream.index();
+        let time_base = stream.time_base();
+
+        let context_decoder =
+            ffmpeg::codec::context::Context::from_parameters(stream.parameters())?;
+        let decoder = context_decoder.decoder().audio()?;
+
+        let resampler = ffmpeg::software::resampling::Context::get(
+            decoder.format(),
+            Self::input_layout(&decoder),
+            decoder.rate(),
+            ffmpeg::format::Sample::F32(ffmpeg::format::sample::Type::Packed),
+            ffmpeg::ChannelLayout::STEREO,
+            sample_rate,
+        )?;
+
+        Ok(Self {
+            file_path: path.to_string(),
+            context,
+            decoder,
+            resampler,
+            stream_index,
+            time_base,
+            eof_sent: false,
+        })
+    }
+
+    // Some containers leave the layout unset; derive it from the channel count
+    fn input_layout(decoder: &ffmpeg::decoder::Audio) -> ffmpeg::ChannelLayout {
+        let layout = decoder.channel_layout();
+        if layout.is_empty() {
+            ffmpeg::ChannelLayout::default(decoder.channels() as i32)
+        } else {
+            layout
+        }
+    }
+
+    /// Appends the next decoded chunk to `out`. Returns its start time in seconds.
+    pub fn decode_next(&mut self, out: &mut Vec<f32>) -> Result<f64> {
+        let mut frame = ffmpeg::util::frame::Audio::empty();
+        let stream_index = self.stream_index;
+
+        loop {
+            // One packet can hold several frames, so drain before reading more
+            if self.decoder.receive_frame(&mut frame).is_ok() {
+                return self.resample(&mut frame, out);
+            }
+
+            if self.eof_sent {
+                anyhow::bail!("End of audio stream");
+            }
+
+            let packet = self
+                .context
+                .packets()
+                .find_map(|(stream, packet)| (stream.index() == stream_index).then_some(packet));
+
+            match packet {
+                Some(packet) => self.decoder.send_packet(&packet)?,
+                None => {
+                    self.decoder.send_eof()?;
+                    self.eof_sent = true;
+                }
+            }
+        }
+    }
+
+    fn resample(&mut self, frame: &mut ffmpeg::util::frame::Audio, out: &mut Vec<f32>) -> Result<f64> {
+        if frame.channel_layout().is_empty() {
+            frame.set_channel_layout(Self::input_layout(&self.decoder));
+        }
+
+        let mut resampled = ffmpeg::util::frame::Audio::empty();
+        self.resampler.run(frame, &mut resampled)?;
+
+        // Packed stereo: one plane, samples * channels floats
+        let len = resampled.samples() * OUTPUT_CHANNELS * std::mem::size_of::<f32>();
+        let bytes = &resampled.data(0)[..len];
+        out.extend(
+            bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]])),
+        );
+
+        let pts = frame.timestamp().or(frame.pts()).unwrap_or(0);
+        Ok(pts as f64 * f64::from(self.time_base))
+    }
+
+    pub fn seek(&mut self, timestamp_seconds: f64) -> Result<()> {
+        // Stream index -1 seeks in AV_TIME_BASE units
+        let target_ts =
+            (timestamp_seconds.max(0.0) * f64::from(ffmpeg::ffi::AV_TIME_BASE)) as i64;
+        self.context.seek(target_ts, ..target_ts)?;
+        self.decoder.flush();
+        self.eof_sent = false;
+        Ok(())
+    }
+}
+
+/// Per-clip mixing state: samples received from the clip's worker, from `position` onward.
+struct ClipAudio {
+    worker: AudioWorker,
+    samples: VecDeque<f32>,
+    position: f64, // Media time of samples.front()
+    aligning: bool, // Next chunk must be trimmed/padded to `position`
+}
+
+impl ClipAudio {
+    fn new(worker: AudioWorker, position: f64) -> Self {
+        Self {
+            worker,
+            samples: VecDeque::new(),
+            position,
+            aligning: true,
+        }
+    }
+
+    fn seek(&mut self, media_time: f64) {
+        self.worker.seek(media_time);
+        self.samples.clear();
+        self.position = media_time;
+        self.aligning = true;
+    }
+
+    /// Adds `out.len() / 2` frames starting at `media_time` into `out`. With `wait`
+    /// it waits for the worker to decode them; otherwise whatever isn't decoded yet
+    /// plays as silence.
+    fn mix_into(&mut self, media_time: f64, sample_rate: u32, out: &mut [f32], wait: bool) {
+        if (media_time - self.position).abs() > RESYNC_TOLERANCE {
+            self.seek(media_time);
+        }
+
+        let rate = f64::from(sample_rate);
+        while self.samples.len() < out.len() {
+            let chunk = if wait { self.worker.wait_next() } else { self.worker.try_next() };
+            let Some(chunk) = chunk else {
+                break;
+            };
+
+            if self.aligning {
+                // Seeks land on packet boundaries and late chunks start before `position`:
+                // drop or pad to the exact sample
+                let lead = ((self.position - chunk.pts) * rate).round() as i64 * OUTPUT_CHANNELS as i64;
+                if lead >= chunk.samples.len() as i64 {
+                    continue;
+                }
+                self.aligning = false;
+                if lead >= 0 {
+                    self.samples.extend(&chunk.samples[lead as usize..]);
+                } else {
+                    self.samples.extend(std::iter::repeat_n(0.0, (-lead) as usize));
+                    self.samples.extend(&chunk.samples);
+                }
+            } else {
+                self.samples.extend(&chunk.samples);
+            }
+        }
+
+        // Samples that arrive after their block was mixed are trimmed off the next one
+        self.aligning |= self.samples.len() < out.len();
+        for sample in out.iter_mut() {
+            *sample += self.samples.pop_front().unwrap_or(0.0);
+        }
+        self.position += (out.len() / OUTPUT_CHANNELS) as f64 / rate;
+    }
+}
+
+/// Mixes every active clip into interleaved stereo blocks. Each clip decodes on
+/// its own `AudioWorker`, opened up to `PREROLL_WINDOW` before the clip starts.
+pub struct AudioMixer {
+    sample_rate: u32,
+    wait: bool, // Wait for decoders (export, tests) instead of mixing silence for late samples
+    clips: HashMap<String, ClipAudio>, // Keyed by clip ID (same file can play twice)
+    silent_paths: HashSet<String>,     // Files without a usable audio stream
+}
+
+impl AudioMixer {
+    /// A mixer for offline use: every block holds all of its samples, however long decoding takes.
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            wait: true,
+            clips: HashMap::new(),
+            silent_paths: HashSet::new(),
+        }
+    }
+
+    /// A mixer for playback: `mix` never waits on a decoder. Samples not decoded
+    /// in time play as silence, and the clip stays in sync.
+    pub fn realtime(sample_rate: u32) -> Self {
+        Self {
+            wait: false,
+            ..Self::new(sample_rate)
+        }
+    }
+
+    /// Mixes `frames` frames of timeline audio starting at `start` (seconds).
+    pub fn mix(&mut self, timeline: &TimelineManager, start: f64, frames: usize) -> Vec<f32> {
+        // Block positions in samples, so clip edges land on exact sample indices
+        let rate = Rational::new(i64::from(self.sample_rate), 1);
+        let start = Time::from_seconds(start, rate);
+        let end = start + Time::from_frames(frames as i64, rate);
+        let mut out = vec![0.0f32; frames * OUTPUT_CHANNELS];
+
+        // Clips anywhere in the block (audio and video tracks), including ones starting and ending inside it
+        let active: Vec<Clip> = timeline
+            .get_audio_clips_in_range(start, end)
+            .into_iter()
+            .cloned()
+            .collect();
+
+        for clip in &active {
+            if self.silent_paths.contains(&clip.path) {
+                continue;
+            }
+
+            // Frame range of this block the clip covers
+            let first = (clip.start.ceil_to(rate).value - start.value).max(0) as usize;
+            let last = (clip.end().ceil_to(rate).value - start.value).clamp(0, frames as i64) as usize;
+            if first >= last {
+                continue;
+            }
+
+            let media_time = clip
+                .get_media_time(start + Time::from_frames(first as i64, rate))
+                .seconds();
+            if !self.open(clip, media_time) {
+                continue;
+            }
+            if let Some(audio) = self.clips.get_mut(&clip.id) {
+                let range = first * OUTPUT_CHANNELS..last * OUTPUT_CHANNELS;
+                audio.mix_into(media_time, self.sample_rate, &mut out[range], self.wait);
+            }
+            self.check_failed(clip);
+        }
+
+        // Start decoding clips that begin soon, so their first block has samples ready
+        let horizon = end + Time::from_seconds(PREROLL_WINDOW, rate);
+        let upcoming: Vec<Clip> = timeline
+            .get_audio_clips_in_range(end, horizon)
+            .into_iter()
+            .filter(|clip| clip.start >= end)
+            .cloned()
+            .collect();
+        for clip in &upcoming {
+            self.open(clip, clip.offset.seconds());
+        }
+
+        // Release decoders for clips that stopped playing
+        self.clips
+            .retain(|id, _| active.iter().chain(&upcoming).any(|c| &c.id == id));
+
+        for sample in out.iter_mut() {
+            *sample = sample.clamp(-1.0, 1.0);
+        }
+        out
+    }
+
+    // Starts a worker for `clip` at `media_time` unless it has one. False if it has no audio
+    fn open(&mut self, clip: &Clip, media_time: f64) -> bool {
+        if self.silent_paths.contains(&clip.path) {
+            return false;
+        }
+        if self.clips.contains_key(&clip.id) {
+            return true;
+        }
+        match AudioWorker::spawn(&clip.path, self.sample_rate, media_time) {
+            Ok(worker) => {
+                self.clips.insert(clip.id.clone(), ClipAudio::new(worker, media_time));
+                true
+            }
+            Err(e) => {
+                println!("Audio: No audio for {}: {}", clip.path, e);
+                self.silent_paths.insert(clip.path.clone());
+                false
+            }
+        }
+    }
+
+    // Drops a clip whose worker couldn't open its audio; its file stays silent from now on
+    fn check_failed(&mut self, clip: &Clip) {
+        let Some(e) = self.clips.get(&clip.id).and_then(|audio| audio.worker.failure()) else {
+            return;
+        };
+        println!("Audio: No audio for {}: {}", clip.path, e);
+        self.silent_paths.insert(clip.path.clone());
+        self.clips.remove(&clip.id);
+    }
+}
+
+/// State shared between the engine and the device callback.
+struct SharedOutput {
+    samples: Mutex<VecDeque<f32>>,
+    frames_played: AtomicU64,
+    running: AtomicBool,
+}
+
+/// The system output device. Consumes mixed samples and counts what it played.
+pub struct AudioOutput {
+    shared: Arc<SharedOutput>,
+    pub sample_rate: u32,
+    _shutdown: mpsc::Sender<()>, // Dropping this stops the stream thread
+}
+
+impl AudioOutput {
+    pub fn open() -> Result<Self> {
+        let shared = Arc::new(SharedOutput {
+            samples: Mutex::new(VecDeque::new()),
+            frames_played: AtomicU64::new(0),
+            running: AtomicBool::new(false),
+        });
+
+        let (ready_tx, ready_rx) = mpsc::channel::<Result<u32, String>>();
+        let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
+        let thread_shared = shared.clone();
+
+        // cpal streams are not Send on every platform, so the stream lives on its own thread
+        std::thread::Builder::new()
+            .name("kenichi-audio".into())
+            .spawn(move || match build_stream(thread_shared) {
+                Ok((stream, sample_rate)) => {
+                    let _ = ready_tx.send(Ok(sample_rate));
+                    let _ = shutdown_rx.recv(); // Blocks until AudioOutput is dropped
+                    drop(stream);
+                }
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e.to_string()));
+                }
+            })?;
+
+        let sample_rate = ready_rx.recv()?.map_err(|e| anyhow::anyhow!(e))?;
+        println!("Audio: Output opened at {} Hz", sample_rate);
+
+        Ok(Self {
+            shared,
+            sample_rate,
+            _shutdown: shutdown_tx,
+        })
+    }
+
+    pub fn set_running(&self, running: bool) {
+        self.shared.running.store(running, Ordering::Release);
+    }
+
+    /// Drops queued audio and restarts the played-frame counter.
+    pub fn reset(&self) {
+        let mut samples = self.shared.samples.lock().unwrap();
+        samples.clear();
+        self.shared.frames_played.store(0, Ordering::Release);
+    }
+
+    pub fn push(&self, block: &[f32]) {
+        self.shared.samples.lock().unwrap().extend(block);
+    }
+
+    pub fn frames_played(&self) -> u64 {
+        self.shared.frames_played.load(Ordering::Acquire)
+    }
+}
+
+fn build_stream(shared: Arc<SharedOutput>) -> Result<(cpal::Stream, u32)> {
+    let host = cpal::default_host();
+    let device = host
+        .default_output_device()
+        .ok_or(anyhow::anyhow!("No audio output device"))?;
+
+    let default_config = device.default_output_config()?;
+    let sample_rate = default_config.sample_rate().0;
+
+    let config = cpal::StreamConfig {
+        channels: default_config.channels(),
+        sample_rate: default_config.sample_rate(),
+        buffer_size: cpal::BufferSize::Default,
+    };
+
+    // The mixer works in f32; convert to whatever the device takes
+    let stream = match default_config.sample_format() {
+        cpal::SampleFormat::F32 => output_stream::<f32>(&device, &config, shared)?,
+        cpal::SampleFormat::I16 => output_stream::<i16>(&device, &config, shared)?,
+        cpal::SampleFormat::U16 => output_stream::<u16>(&device, &config, shared)?,
+        format => anyhow::bail!("Unsupported output sample format: {}", format),
+    };
+    stream.play()?;
+
+    Ok((stream, sample_rate))
+}
+
+fn output_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    shared: Arc<SharedOutput>,
+) -> Result<cpal::Stream>
+where
+    T: cpal::SizedSample + cpal::FromSample<f32>,
+{
+    let channels = config.channels as usize;
+    let stream = device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| write_output(&shared, data, channels),
+        |e| eprintln!("Audio Output Error: {}", e),
+        None,
+    )?;
+    Ok(stream)
+}
+
+// Device callback: maps stereo onto the device layout, silence on underrun
+fn write_output<T>(shared: &SharedOutput, data: &mut [T], channels: usize)
+where
+    T: cpal::Sample + cpal::FromSample<f32>,
+{
+    data.fill(T::EQUILIBRIUM);
+    if !shared.running.load(Ordering::Acquire) {
+        return;
+    }
+
+    let Ok(mut samples) = shared.samples.lock() else {
+        return;
+    };
+
+    let mut played = 0;
+    for frame in data.chunks_exact_mut(channels) {
+        if samples.len() < OUTPUT_CHANNELS {
+            break; // Underrun: the clock holds until the mixer catches up
+        }
+        let left = samples.pop_front().unwrap_or(0.0);
+        let right = samples.pop_front().unwrap_or(0.0);
+
+        if channels == 1 {
+            frame[0] = T::from_sample((left + right) * 0.5);
+        } else {
+            frame[0] = T::from_sample(left);
+            frame[1] = T::from_sample(right);
+        }
+        played += 1;
+    }
+
+    shared.frames_played.fetch_add(played, Ordering::AcqRel);
+}
+
+/// Timeline audio playback. When an output device is open, its played-sample
+/// count is the master clock for `PlaybackState::current_time`. The device opens
+/// on first play, so headless engines (export, tests) never touch it.
+pub struct AudioPlayback {
+    mixer: AudioMixer,
+    output: Option<AudioOutput>,
+    output_tried: bool, // Don't retry a missing device on every play
+    base_time: f64, // Timeline time when the played-frame counter was reset
+    mix_time: f64,  // Timeline time of the next sample to mix
+}
+
+impl Default for AudioPlayback {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioPlayback {
+    pub fn new() -> Self {
+        Self {
+            mixer: AudioMixer::realtime(DEFAULT_SAMPLE_RATE),
+            output: None,
+            output_tried: false,
+            base_time: 0.0,
+            mix_time: 0.0,
+        }
+    }
+
+    fn open_output(&mut self) {
+        if self.output_tried {
+            return;
+        }
+        self.output_tried = true;
+
+        match AudioOutput::open() {
+            Ok(output) => {
+                self.mixer = AudioMixer::realtime(output.sample_rate); // Mix at the device rate
+                self.output = Some(output);
+            }
+            Err(e) => {
+                eprintln!("Audio: Output unavailable, falling back to wall clock: {}", e);
+            }
+        }
+    }
+
+    pub fn start(&mut self, time: f64) {
+        self.open_output();
+        self.reset(time);
+        if let Some(output) = &self.output {
+            output.set_running(true);
+        }
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(output) = &self.output {
+            output.set_running(false);
+            output.reset();
+        }
+    }
+
+    /// Restarts the clock at `time` (seek or play).
+    pub fn reset(&mut self, time: f64) {
+        if let Some(output) = &self.output {
+            output.reset();
+        }
+        self.base_time = time;
+        self.mix_time = time;
+    }
+
+    /// Current timeline time according to the audio device, if one is open.
+    pub fn clock_time(&self) -> Option<f64> {
+        self.output.as_ref().map(|output| {
+            self.base_time + output.frames_played() as f64 / f64::from(output.sample_rate)
+        })
+    }
+
+    /// Mixes ahead of the clock so the device never starves.
+    pub fn fill(&mut self, timeline: &TimelineManager) {
+        let (Some(output), Some(now)) = (&self.output, self.clock_time()) else {
+            return;
+        };
+
+        let block_duration = MIX_BLOCK_FRAMES as f64 / f64::from(output.sample_rate);
+        while self.mix_time < now + MIX_LEAD {
+            let block = self.mixer.mix(timeline, self.mix_time, MIX_BLOCK_FRAMES);
+            output.push(&block);
+            self.mix_time += block_duration;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    use crate::engine::export_utils::ffmpeg_binary;
+
+    const RATE: u32 = 48_000;
+    const LEVEL: f32 = 0.25; // Tone clips hold this constant level on both channels
+
+    // A constant stereo tone as float WAV, so decoded samples are exact.
+    // Unique per process, since tests run in parallel and across checkouts.
+    fn synthesize_tone(name: &str, seconds: f64) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("kenichi_audio_{}_{}.wav", name, std::process::id()));
+        let source = format!("aevalsrc={0}|{0}:s={1}:d={2}", LEVEL, RATE, seconds);
+        let status = std::process::Command::new(ffmpeg_binary())
+            .args(["-f", "lavfi", "-i", &source, "-c:a", "pcm_f32le", "-y"])
+            .arg(&path)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status();
+        match status {
+            Ok(status) if status.success() => path,
+            _ => panic!("Audio tests need the ffmpeg CLI to synthesize a tone (tried {})", ffmpeg_binary()),
+        }
+    }
+
+    fn tone_clip(id: &str, path: &Path, track_id: i32, start: i64, frames: i64) -> Clip {
+        let fps = Rational::new(25, 1);
+        Clip {
+            id: id.into(),
+            path: path.to_string_lossy().into_owned(),
+            start: Time::from_frames(start, fps),
+            duration: Time::from_frames(frames, fps),
+            track_id,
+            ..Default::default()
+        }
+    }
+
+    // Stereo frames of `block` not at `level` on both channels
+    fn frames_off_level(block: &[f32], frames: std::ops::Range<usize>, level: f32) -> Vec<usize> {
+        frames
+            .filter(|&i| block[i * 2..i * 2 + 2].iter().any(|s| (s - level).abs() > 1e-6))
+            .collect()
+    }
+
+    #[test]
+    #[ignore = "needs the ffmpeg CLI; run with --ignored"]
+    fn test_mixes_clip_inside_one_block() {
+        let path = synthesize_tone("inside", 1.0);
+        let mut timeline = TimelineManager::new();
+        timeline.add_clip(tone_clip("tone", &path, 1, 1, 1)).unwrap(); // Samples 1920..3840
+
+        // Neither the block's first nor its last sample is covered
+        let block = AudioMixer::new(RATE).mix(&timeline, 0.0, 4800);
+        assert_eq!(block.len(), 4800 * OUTPUT_CHANNELS);
+        assert!(frames_off_level(&block, 0..1920, 0.0).is_empty());
+        assert!(frames_off_level(&block, 1920..3840, LEVEL).is_empty());
+        assert!(frames_off_level(&block, 3840..4800, 0.0).is_empty());
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    #[ignore = "needs the ffmpeg CLI; run with --ignored"]
+    fn test_mix_sums_tracks_and_follows_clip_edges() {
+        let path = synthesize_tone("sum", 1.0);
+        let mut timeline = TimelineManager::new();
+        timeline.add_clip(tone_clip("a", &path, 1, 0, 5)).unwrap(); // Samples 0..9600
+        timeline.add_clip(tone_clip("b", &path, 2, 2, 5)).unwrap(); // Samples 3840..13440
+
+        // Blocks straddle both clip edges; consecutive blocks continue the same decoders
+        let mut mixer = AudioMixer::new(RATE);
+        let mut mixed = Vec::new();
+        for block in 0..15 {
+            mixed.extend(mixer.mix(&timeline, f64::from(block * 1024) / f64::from(RATE), 1024));
+        }
+        assert!(frames_off_level(&mixed, 0..3840, LEVEL).is_empty());
+        assert!(frames_off_level(&mixed, 3840..9600, LEVEL * 2.0).is_empty());
+        assert!(frames_off_level(&mixed, 9600..13440, LEVEL).is_empty());
+        assert!(frames_off_level(&mixed, 13440..15 * 1024, 0.0).is_empty());
+
+        // Muted tracks drop out
+        timeline.set_track_muted(2, true).unwrap();
+        let block = AudioMixer::new(RATE).mix(&timeline, 0.1, 1024); // Sample 4800
+        assert!(frames_off_level(&block, 0..1024, LEVEL).is_empty());
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    #[ignore = "needs the ffmpeg CLI; run with --ignored"]
+    fn test_realtime_mix_catches_up_in_sync() {
+        let path = synthesize_tone("realtime", 1.0);
+        let mut timeline = TimelineManager::new();
+        timeline.add_clip(tone_clip("tone", &path, 1, 0, 25)).unwrap();
+
+        // The first block is mixed before the worker can have opened the file
+        let mut mixer = AudioMixer::realtime(RATE);
+        assert_eq!(mixer.mix(&timeline, 0.0, 1024).len(), 1024 * OUTPUT_CHANNELS);
+
+        // Once it has, the tone is back where the mix is, not where decoding started
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        let block = mixer.mix(&timeline, 1024.0 / f64::from(RATE), 1024);
+        assert!(frames_off_level(&block, 0..1024, LEVEL).is_empty());
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_missing_media_mixes_silence() {
+        let mut timeline = TimelineManager::new();
+        let missing = std::env::temp_dir().join("kenichi_audio_missing.wav");
+        timeline.add_clip(tone_clip("missing", &missing, 1, 0, 5)).unwrap();
+
+        let mut mixer = AudioMixer::new(RATE);
+        let block = mixer.mix(&timeline, 0.0, 1024);
+        assert!(frames_off_level(&block, 0..1024, 0.0).is_empty());
+        assert!(mixer.silent_paths.contains(&timeline.timeline.clips["missing"].path));
+        assert!(mixer.clips.is_empty());
+    }
+
+    #[test]
+    fn test_output_converts_to_device_samples() {
+        let shared = SharedOutput {
+            samples: Mutex::new(VecDeque::from([0.5, -0.5, 1.0, 0.0])),
+            frames_played: AtomicU64::new(0),
+            running: AtomicBool::new(true),
+        };
+
+        // Stereo i16: two frames, then underrun silence
+        let mut data = [1i16; 6];
+        write_output(&shared, &mut data, 2);
+        assert_eq!(data, [16384, -16384, i16::MAX, 0, 0, 0]);
+        assert_eq!(shared.frames_played.load(Ordering::Acquire), 2);
+
+        // Mono u16 downmixes; silence is the midpoint
+        shared.samples.lock().unwrap().extend([0.5, 0.0]);
+        let mut data = [0u16; 2];
+        write_output(&shared, &mut data, 1);
+        assert_eq!(data, [40960, 32768]);
+        assert_eq!(shared.frames_played.load(Ordering::Acquire), 3);
+    }
+}
@@ -0,0 +1,183 @@
+use anyhow::Result;
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender, TryRecvError};
+use std::thread;
+
+use super::audio::AudioDecoder;
+
+// Chunks decoded ahead of the mixer (about a second of typical 1024-sample frames);
+// the thread sleeps while the queue is full
+const QUEUE_CAPACITY: usize = 48;
+
+/// Interleaved stereo samples at the mixer rate, and the media time of the first one.
+pub struct AudioChunk {
+    pub samples: Vec<f32>,
+    pub pts: f64, // Seconds
+}
+
+enum Request {
+    Seek { time: f64, generation: u64 },
+}
+
+enum Event {
+    Failed(String),
+    Chunk { chunk: AudioChunk, generation: u64 },
+    EndOfStream { generation: u64 },
+}
+
+/// Decodes and resamples one clip's audio on its own thread into a bounded queue,
+/// so mixing under the engine mutex never opens a file or decodes a packet.
+/// The consumer side mirrors `DecodeWorker`.
+pub struct AudioWorker {
+    requests: Sender<Request>, // Dropping it stops the thread
+    events: Receiver<Event>,
+    generation: u64, // Bumped by every seek; chunks from before it are discarded
+    failure: Option<String>, // Why the audio couldn't be opened; no chunks will come
+    end_of_stream: bool, // No more chunks until the next seek
+}
+
+impl AudioWorker {
+    /// Starts decoding `path` at `time` (seconds), resampled to `sample_rate`.
+    /// Opening happens on the thread too.
+    pub fn spawn(path: &str, sample_rate: u32, time: f64) -> Result<Self> {
+        let (request_tx, request_rx) = mpsc::channel();
+        let (event_tx, event_rx) = mpsc::sync_channel(QUEUE_CAPACITY);
+        let thread_path = path.to_string();
+        thread::Builder::new()
+            .name(format!("audio {}", path))
+            .spawn(move || run(thread_path, sample_rate, time, request_rx, event_tx))?;
+
+        Ok(Self {
+            requests: request_tx,
+            events: event_rx,
+            generation: 0,
+            failure: None,
+            end_of_stream: false,
+        })
+    }
+
+    /// Restarts decoding at `time` (seconds). Chunks already queued are dropped.
+    pub fn seek(&mut self, time: f64) {
+        self.generation += 1;
+        self.end_of_stream = false;
+        let _ = self.requests.send(Request::Seek {
+            time,
+            generation: self.generation,
+        });
+        // Free the queue so a thread blocked on it picks up the request
+        while self.try_next().is_some() {}
+    }
+
+    /// The next chunk since the last seek, if one is ready.
+    pub fn try_next(&mut self) -> Option<AudioChunk> {
+        loop {
+            let chunk = self.handle(self.events.try_recv().ok()?);
+            if chunk.is_some() {
+                return chunk;
+            }
+        }
+    }
+
+    /// The next chunk since the last seek, waiting for the thread to decode it.
+    /// None at the end of the stream or if the file failed to open.
+    pub fn wait_next(&mut self) -> Option<AudioChunk> {
+        while !self.end_of_stream && self.failure.is_none() {
+            let chunk = self.handle(self.events.recv().ok()?);
+            if chunk.is_some() {
+                return chunk;
+            }
+        }
+        None
+    }
+
+    /// Why the thread gave up, once it reported failing to open the audio.
+    pub fn failure(&self) -> Option<&str> {
+        self.failure.as_deref()
+    }
+
+    // Returns the chunk if the event is a current one
+    fn handle(&mut self, event: Event) -> Option<AudioChunk> {
+        match event {
+            Event::Failed(e) => self.failure = Some(e),
+            Event::Chunk { chunk, generation } if generation == self.generation => return Some(chunk),
+            Event::Chunk { .. } => {} // From before the last seek
+            Event::EndOfStream { generation } => self.end_of_stream |= generation == self.generation,
+        }
+        None
+    }
+}
+
+// Thread body: decode forward until the queue is full, follow the newest seek
+fn run(path: String, sample_rate: u32, start: f64, requests: Receiver<Request>, events: SyncSender<Event>) {
+    let mut decoder = match AudioDecoder::new(&path, sample_rate) {
+        Ok(decoder) => decoder,
+        Err(e) => {
+            let _ = events.send(Event::Failed(e.to_string()));
+            return;
+        }
+    };
+    if start > 0.0 {
+        if let Err(e) = decoder.seek(start) {
+            eprintln!("Audio Worker: Seek failed in {}: {}", path, e);
+        }
+    }
+
+    let mut generation = 0;
+    let mut decoding = true;
+    loop {
+        // At the end of the stream, sleep until told to seek
+        let request = if decoding {
+            match requests.try_recv() {
+                Ok(request) => Some(request),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        } else {
+            match requests.recv() {
+                Ok(request) => Some(request),
+                Err(_) => return,
+            }
+        };
+
+        if let Some(mut request) = request {
+            // Only the last of several queued seeks matters
+            while let Ok(newer) = requests.try_recv() {
+                request = newer;
+            }
+            let Request::Seek { time, generation: next } = request;
+            generation = next;
+            decoding = true;
+            if let Err(e) = decoder.seek(time) {
+                eprintln!("Audio Worker: Seek failed in {}: {}", path, e);
+            }
+        }
+
+        // Blocks while the queue is full; fails once the consumer is gone
+        let mut samples = Vec::new();
+        let event = match decoder.decode_next(&mut samples) {
+            Ok(pts) => Event::Chunk {
+                chunk: AudioChunk { samples, pts },
+                generation,
+            },
+            Err(_) => {
+                decoding = false;
+                Event::EndOfStream { generation }
+            }
+        };
+        if events.send(event).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_file_reports_failure() {
+        let mut worker = AudioWorker::spawn("/nonexistent/kenichi_missing.wav", 48_000, 0.0).unwrap();
+        assert!(worker.wait_next().is_none()); // Waits for the thread to give up
+        assert!(worker.failure().is_some());
+        assert!(worker.try_next().is_none());
+    }
+}
@@ -0,0 +1,252 @@
+use anyhow::Result;
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender, TryRecvError};
+use std::thread;
+
+use super::decoding::VideoDecoder;
+use super::hwaccel::DecodePath;
+use super::time::{Rational, Time};
+use super::video_frame::VideoFrame;
+
+// Frames decoded ahead of the consumer; the thread sleeps while the queue is full
+const QUEUE_CAPACITY: usize = 6;
+
+#[derive(Clone, Copy, Debug)]
+pub struct StreamInfo {
+    pub width: u32,
+    pub height: u32,
+    pub frame_rate: Rational,
+    pub decode_path: DecodePath,
+}
+
+pub struct DecodedFrame {
+    pub frame: VideoFrame,
+    pub pts: Time,
+}
+
+enum Request {
+    Seek { time: Time, generation: u64 },
+}
+
+enum Event {
+    Opened(StreamInfo),
+    Failed(String),
+    Frame { frame: DecodedFrame, generation: u64 },
+    EndOfStream { generation: u64 },
+}
+
+/// Decodes one file on its own thread into a bounded queue of frames, so the
+/// engine (and the mutex around it) never waits on FFmpeg. Only the consumer
+/// side lives here; it never blocks.
+pub struct DecodeWorker {
+    requests: Sender<Request>, // Dropping it stops the thread
+    events: Receiver<Event>,
+    generation: u64, // Bumped by every seek; frames from before it are discarded
+    info: Option<StreamInfo>,
+    failure: Option<String>, // Why the file couldn't be opened; no frames will come
+    end_of_stream: bool, // No more frames until the next seek
+}
+
+impl DecodeWorker {
+    /// Starts decoding `path` from the beginning. Opening happens on the thread too;
+    /// `info` is None until it has.
+    pub fn spawn(path: &str) -> Result<Self> {
+        let (request_tx, request_rx) = mpsc::channel();
+        let (event_tx, event_rx) = mpsc::sync_channel(QUEUE_CAPACITY);
+        let thread_path = path.to_string();
+        thread::Builder::new()
+            .name(format!("decode {}", path))
+            .spawn(move || run(thread_path, request_rx, event_tx))?;
+
+        Ok(Self {
+            requests: request_tx,
+            events: event_rx,
+            generation: 0,
+            info: None,
+            failure: None,
+            end_of_stream: false,
+        })
+    }
+
+    /// Stream size and rate, once the file is open.
+    pub fn info(&mut self) -> Option<StreamInfo> {
+        if self.info.is_none() {
+            // `Opened` (or `Failed`) is always the first event
+            self.handle(self.events.try_recv().ok()?);
+        }
+        self.info
+    }
+
+    /// Why the thread gave up, if opening the file failed.
+    pub fn failure(&mut self) -> Option<&str> {
+        self.info();
+        self.failure.as_deref()
+    }
+
+    /// Restarts decoding at `time`. Frames already queued are dropped.
+    pub fn seek(&mut self, time: Time) {
+        self.generation += 1;
+        self.end_of_stream = false;
+        let _ = self.requests.send(Request::Seek {
+            time,
+            generation: self.generation,
+        });
+        // Free the queue so a thread blocked on it picks up the request
+        while self.try_next().is_some() {}
+    }
+
+    /// The next decoded frame since the last seek, if one is ready.
+    pub fn try_next(&mut self) -> Option<DecodedFrame> {
+        loop {
+            let frame = self.handle(self.events.try_recv().ok()?);
+            if frame.is_some() {
+                return frame;
+            }
+        }
+    }
+
+    pub fn is_end_of_stream(&self) -> bool {
+        self.end_of_stream
+    }
+
+    // Returns the frame if the event is a current one
+    fn handle(&mut self, event: Event) -> Option<DecodedFrame> {
+        match event {
+            Event::Opened(info) => self.info = Some(info),
+            Event::Failed(e) => {
+                eprintln!("Decode Worker: {}", e);
+                self.failure = Some(e);
+            }
+            Event::Frame { frame, generation } if generation == self.generation => return Some(frame),
+            Event::Frame { .. } => {} // From before the last seek
+            Event::EndOfStream { generation } => self.end_of_stream |= generation == self.generation,
+        }
+        None
+    }
+}
+
+// Thread body: decode forward until the queue is full, follow the newest seek
+fn run(path: String, requests: Receiver<Request>, events: SyncSender<Event>) {
+    let mut decoder = match VideoDecoder::new(&path) {
+        Ok(decoder) => decoder,
+        Err(e) => {
+            let _ = events.send(Event::Failed(format!("Failed to open {}: {}", path, e)));
+            return;
+        }
+    };
+    let info = StreamInfo {
+        width: decoder.width(),
+        height: decoder.height(),
+        frame_rate: decoder.frame_rate,
+        decode_path: decoder.decode_path,
+    };
+    if events.send(Event::Opened(info)).is_err() {
+        return;
+    }
+
+    let mut generation = 0;
+    let mut decoding = true;
+    loop {
+        // At the end of the stream, sleep until told to seek
+        let request = if decoding {
+            match requests.try_recv() {
+                Ok(request) => Some(request),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        } else {
+            match requests.recv() {
+                Ok(request) => Some(request),
+                Err(_) => return,
+            }
+        };
+
+        if let Some(mut request) = request {
+            // Scrubbing queues many seeks; only the last one matters
+            while let Ok(newer) = requests.try_recv() {
+                request = newer;
+            }
+            let Request::Seek { time, generation: next } = request;
+            generation = next;
+            decoding = true;
+            if let Err(e) = decoder.seek(time) {
+                eprintln!("Decode Worker: Seek failed in {}: {}", path, e);
+            }
+        }
+
+        // Blocks while the queue is full; fails once the consumer is gone
+        let event = match decoder.decode_next_frame() {
+            Ok((frame, pts)) => Event::Frame {
+                frame: DecodedFrame { frame, pts },
+                generation,
+            },
+            Err(_) => {
+                decoding = false;
+                Event::EndOfStream { generation }
+            }
+        };
+        if events.send(event).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::decoding::generate_test_clip;
+    use std::time::{Duration, Instant};
+
+    fn wait_for<T>(mut poll: impl FnMut() -> Option<T>) -> T {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            if let Some(value) = poll() {
+                return value;
+            }
+            assert!(Instant::now() < deadline, "timed out waiting for the decode thread");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    #[ignore = "needs the ffmpeg CLI; run with --ignored"]
+    fn test_frames_follow_seeks() {
+        let path = generate_test_clip("worker");
+        let mut worker = DecodeWorker::spawn(path.to_str().unwrap()).unwrap();
+        let info = wait_for(|| worker.info());
+        assert_eq!((info.width, info.height), (160, 120));
+        let fps = info.frame_rate;
+
+        // Decodes from the start without being asked
+        assert_eq!(wait_for(|| worker.try_next()).pts, Time::from_frames(0, fps));
+
+        // Frames queued before a seek never show up after it
+        worker.seek(Time::from_frames(30, fps));
+        worker.seek(Time::from_frames(20, fps));
+        for frame in 20..26 {
+            let decoded = wait_for(|| worker.try_next());
+            assert_eq!(decoded.pts, Time::from_frames(frame, fps));
+            assert_eq!((decoded.frame.width, decoded.frame.height), (160, 120));
+        }
+
+        // Runs to the end, then waits for the next seek
+        worker.seek(Time::from_frames(48, fps));
+        assert_eq!(wait_for(|| worker.try_next()).pts, Time::from_frames(48, fps));
+        assert_eq!(wait_for(|| worker.try_next()).pts, Time::from_frames(49, fps));
+        wait_for(|| {
+            worker.try_next();
+            worker.is_end_of_stream().then_some(())
+        });
+
+        drop(worker);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_missing_file_reports_failure() {
+        let mut worker = DecodeWorker::spawn("/nonexistent/kenichi_missing.mp4").unwrap();
+        let failure = wait_for(|| worker.failure().map(str::to_string));
+        assert!(failure.contains("kenichi_missing.mp4"), "{}", failure);
+        assert!(worker.info().is_none());
+        assert!(worker.try_next().is_none());
+    }
+}
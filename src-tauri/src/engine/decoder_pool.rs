@@ -0,0 +1,60 @@
+use super::layer::ClipLayer;
+use super::time::Time;
+
+// Layers kept open after their clip ends or before it starts
+const MAX_WARM_DECODERS: usize = 5;
+
+/// Warm layers (decoder, texture and bind group) that are open but not on screen,
+/// keyed by source path. Clips starting soon are pre-rolled here, and layers of
+/// clips that just ended wait here, so cuts and scrubbing back and forth across
+/// them don't reopen the file.
+#[derive(Default)]
+pub struct DecoderPool {
+    layers: Vec<ClipLayer>, // Least recently used first
+}
+
+impl DecoderPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Removes the warm layer for `path` closest to `media_time`, if any.
+    pub fn take(&mut self, path: &str, media_time: Time) -> Option<ClipLayer> {
+        let index = self
+            .layers
+            .iter()
+            .enumerate()
+            .filter(|(_, layer)| layer.path == path)
+            .min_by_key(|(_, layer)| (layer.pts - media_time).abs())
+            .map(|(index, _)| index)?;
+        Some(self.layers.remove(index))
+    }
+
+    /// Keeps a layer warm, closing the least recently used one if the pool is full.
+    pub fn put(&mut self, layer: ClipLayer) {
+        if self.layers.len() >= MAX_WARM_DECODERS {
+            let closed = self.layers.remove(0);
+            println!("Engine: Closing warm decoder -> {}", closed.path);
+        }
+        self.layers.push(layer);
+    }
+
+    /// True if a layer for `path` shows the frame at `media_time` or is decoding towards it.
+    pub fn is_ready(&mut self, path: &str, media_time: Time) -> bool {
+        self.layers
+            .iter_mut()
+            .any(|layer| layer.path == path && layer.is_cued(media_time))
+    }
+
+    pub fn clear(&mut self) {
+        self.layers.clear();
+    }
+}
@@ -1,6 +1,12 @@
 use anyhow::Result;
+use std::collections::VecDeque;
 use std::path::Path;
 
+use super::frame_pool::FramePool;
+use super::hwaccel::{self, DecodePath};
+use super::time::{Rational, Time, DEFAULT_FRAME_RATE};
+use super::video_frame::VideoFrame;
+
 pub struct VideoDecoder {
     pub file_path: String,
     // TODO: Add ffmpeg context fields here
@@ -8,17 +14,39 @@ pub struct VideoDecoder {
     decoder: ffmpeg::decoder::Video,
     scaler: Option<ffmpeg::software::scaling::Context>,
     stream_index: usize,
-    time_base: ffmpeg::Rational, // To convert PTS to seconds
-    pub fps: f64,                // [NEW] Frames per second
+    pts_rate: Rational,       // Stream ticks per second (inverse of the time base)
+    pub frame_rate: Rational, // e.g. 24000/1001
+    output_size: Option<(u32, u32)>, // Scale target (None = source size)
+    pending: VecDeque<ffmpeg::util::frame::Video>, // Decoded by `seek`, returned before new packets are read
+    pub decode_path: DecodePath, // Device the frames come from (see hwaccel.rs)
+    pool: FramePool, // Plane buffers of the frames handed out, back once they are dropped
+    spare: Option<ffmpeg::util::frame::Video>, // Reused for every decoded frame
+    scaled: ffmpeg::util::frame::Video, // Reused swscale output (allocated on first use)
 }
 
-// SAFETY: VideoDecoder is stored in KinetixEngine, which is wrapped in a Mutex in AppState.
-// We guarantee that we only access it from one thread at a time (via the Mutex lock).
-// The underlying raw pointers in ffmpeg contexts are not shared across threads concurrently.
+// SAFETY: Decoders are moved into their DecodeWorker thread and only used there (export
+// renders through the same layers). A decoder is never used from two threads at once,
+// and the raw pointers in its ffmpeg contexts are not shared.
 unsafe impl Send for VideoDecoder {}
 
 impl VideoDecoder {
+    /// Opens `path` on a hardware decoder if the `hwaccel` preference allows one and
+    /// it decodes the first frame, otherwise in software.
     pub fn new(path: &str) -> Result<Self> {
+        let candidates = hwaccel::candidates(hwaccel::preference());
+        if !candidates.is_empty() {
+            match Self::open(path, &candidates).and_then(|mut decoder| decoder.probe().map(|_| decoder)) {
+                Ok(decoder) => {
+                    println!("Decoder: {} -> {:?}", path, decoder.decode_path);
+                    return Ok(decoder);
+                }
+                Err(e) => println!("Decoder: Hardware decoding failed for {} ({}), using software", path, e),
+            }
+        }
+        Self::open(path, &[])
+    }
+
+    fn open(path: &str, candidates: &[DecodePath]) -> Result<Self> {
         ffmpeg::init()?; // Initialize FFmpeg
 
         // Open the input file
@@ -32,11 +60,24 @@ impl VideoDecoder {
 
         let stream_index = stream.index();
         let time_base = stream.time_base();
-        let fps = f64::from(stream.rate());
+        if time_base.numerator() <= 0 || time_base.denominator() <= 0 {
+            anyhow::bail!("Invalid stream time base: {:?}", time_base);
+        }
+        let pts_rate = Rational::new(
+            i64::from(time_base.denominator()),
+            i64::from(time_base.numerator()),
+        );
+        let rate = stream.rate();
+        let frame_rate = if rate.numerator() > 0 && rate.denominator() > 0 {
+            Rational::new(i64::from(rate.numerator()), i64::from(rate.denominator()))
+        } else {
+            DEFAULT_FRAME_RATE // Unknown (e.g. variable rate without a hint)
+        };
 
         // Create a decoder for the stream
-        let context_decoder =
+        let mut context_decoder =
             ffmpeg::codec::context::Context::from_parameters(stream.parameters())?;
+        let decode_path = hwaccel::attach(&mut context_decoder, candidates).unwrap_or(DecodePath::Software);
         let decoder = context_decoder.decoder().video()?;
 
         Ok(Self {
@@ -45,93 +86,143 @@ impl VideoDecoder {
             decoder,
             scaler: None,
             stream_index,
-            time_base,
-            fps,
+            pts_rate,
+            frame_rate,
+            output_size: None,
+            pending: VecDeque::new(),
+            decode_path,
+            pool: FramePool::new(),
+            spare: None,
+            scaled: ffmpeg::util::frame::Video::empty(),
         })
     }
 
-    pub fn decode_next_frame(&mut self) -> Result<(Vec<u8>, f64)> {
-        let mut decoded_frame = ffmpeg::util::frame::Video::empty();
+    // Decodes the first frame (and puts it back), so a device that can't handle
+    // the stream fails here rather than during playback
+    fn probe(&mut self) -> Result<()> {
+        let (_, pts) = self.decode_next_frame()?;
+        self.seek(pts)
+    }
+
+    /// Scales decoded frames to the given size instead of the source size, as RGBA,
+    /// for consumers that read pixels on the CPU rather than sampling planes on the GPU.
+    pub fn set_output_size(&mut self, width: u32, height: u32) {
+        self.output_size = Some((width, height));
+        self.scaler = None; // Rebuilt lazily with the new target
+        self.scaled = ffmpeg::util::frame::Video::empty(); // swscale won't write a different size
+    }
+
+    /// Returns the next frame and its media time (exact, in stream ticks).
+    /// 8-bit 4:2:0 frames keep their YUV planes for the shader; other formats,
+    /// and every frame once `set_output_size` was called, come as RGBA.
+    pub fn decode_next_frame(&mut self) -> Result<(VideoFrame, Time)> {
+        // Frames `seek` already pulled out of the decoder come first
+        if let Some(frame) = self.pending.pop_front() {
+            let video_frame = self.process_frame(&frame)?;
+            let time = Time::from_frames(frame.pts().unwrap_or(0), self.pts_rate);
+            self.spare = Some(frame);
+            return Ok((video_frame, time));
+        }
+
+        // The same frame is decoded into every time; FFmpeg unrefs the previous picture
+        let mut decoded_frame = self.spare.take().unwrap_or_else(ffmpeg::util::frame::Video::empty);
+        let result = self.receive_frame(&mut decoded_frame).and_then(|()| {
+            // Frame decoded! Now copy (or scale) it.
+            let video_frame = self.process_frame(&decoded_frame)?;
+            Ok((video_frame, Time::from_frames(decoded_frame.pts().unwrap_or(0), self.pts_rate)))
+        });
+        self.spare = Some(decoded_frame);
+        result
+    }
 
+    // Reads packets until the decoder produces a frame, draining it at the end of the stream
+    fn receive_frame(&mut self, decoded_frame: &mut ffmpeg::util::frame::Video) -> Result<()> {
         // Iterate through packets until we get a full frame
         for (stream, packet) in self.context.packets() {
             if stream.index() == self.stream_index {
                 self.decoder.send_packet(&packet)?;
                 // Keep trying to receive frame
-                if self.decoder.receive_frame(&mut decoded_frame).is_ok() {
-                    // Frame decoded! Now scale it.
-                    let pixels = self.process_frame(&decoded_frame)?;
-
-                    // Calculate Timestamp (seconds)
-                    let pts = decoded_frame.pts().unwrap_or(0);
-                    let seconds = pts as f64 * f64::from(self.time_base);
-
-                    if pts == 0 {
+                if self.decoder.receive_frame(decoded_frame).is_ok() {
+                    if decoded_frame.pts().unwrap_or(0) == 0 {
                         println!(
-                            "Decoder: Warning! Frame PTS is 0. TimeBase: {:?}",
-                            self.time_base
+                            "Decoder: Warning! Frame PTS is 0. Ticks/s: {}",
+                            self.pts_rate
                         );
-                    } else {
-                        // println!("Decoder: Decoded Frame at {:.3}s (PTS: {})", seconds, pts);
                     }
-
-                    return Ok((pixels, seconds));
+                    return Ok(());
                 }
             }
         }
 
-        // Flush decoder if EOF
-        self.decoder.send_eof()?;
-        if self.decoder.receive_frame(&mut decoded_frame).is_ok() {
-            let pixels = self.process_frame(&decoded_frame)?;
-
-            let pts = decoded_frame.pts().unwrap_or(0);
-            let seconds = pts as f64 * f64::from(self.time_base);
-
-            return Ok((pixels, seconds));
+        // Flush decoder if EOF (a seek near the end may already have started draining)
+        let _ = self.decoder.send_eof();
+        if self.decoder.receive_frame(decoded_frame).is_ok() {
+            return Ok(());
         }
 
         Err(anyhow::anyhow!("End of stream or no frame produced"))
     }
 
-    fn process_frame(&mut self, frame: &ffmpeg::util::frame::Video) -> Result<Vec<u8>> {
+    fn process_frame(&mut self, frame: &ffmpeg::util::frame::Video) -> Result<VideoFrame> {
+        // Hardware frames are copied to system memory first
+        let downloaded;
+        let frame = if hwaccel::is_hardware_frame(frame) {
+            downloaded = hwaccel::download(frame)?;
+            &downloaded
+        } else {
+            if self.decode_path != DecodePath::Software {
+                // The device turned the stream down (profile, size), FFmpeg decodes in software
+                println!("Decoder: {:?} unavailable for {}, decoding in software", self.decode_path, self.file_path);
+                self.decode_path = DecodePath::Software;
+            }
+            frame
+        };
+
+        // YUV planes go to the GPU as they are (see video.wgsl)
+        if self.output_size.is_none() {
+            if let Some(video_frame) = VideoFrame::from_yuv(frame, &self.pool) {
+                return Ok(video_frame);
+            }
+        }
+
         // Initialize scaler if needed (lazy init ensures correct input dimensions)
         if self.scaler.is_none() {
+            let (out_width, out_height) = self
+                .output_size
+                .unwrap_or((frame.width(), frame.height()));
             self.scaler = Some(ffmpeg::software::scaling::Context::get(
                 frame.format(),
                 frame.width(),
                 frame.height(),
                 ffmpeg::format::Pixel::RGBA, // WGPU friendly
-                frame.width(),
-                frame.height(),
+                out_width,
+                out_height,
                 ffmpeg::software::scaling::flag::Flags::BILINEAR,
             )?);
         }
 
-        let mut rgb_frame = ffmpeg::util::frame::Video::empty();
         if let Some(scaler) = &mut self.scaler {
-            scaler.run(frame, &mut rgb_frame)?;
+            scaler.run(frame, &mut self.scaled)?;
         }
 
-        // Copy raw bytes
-        // RGBA is packed, so stride is typically width * 4.
-        // We need to flatten the buffer.
-        let data = rgb_frame.data(0);
-        let stride = rgb_frame.stride(0);
-        let width = rgb_frame.width() as usize;
-        let height = rgb_frame.height() as usize;
-
-        // If tightly packed, copy directly. If padded, copy row by row.
-        // Usually stride == width * 4 for RGBA, but usage might differ.
-        let mut pixels = Vec::with_capacity(width * height * 4);
-
-        for i in 0..height {
-            let start = i * stride;
-            let end = start + (width * 4);
-            pixels.extend_from_slice(&data[start..end]);
+        // RGBA is read row by row on the CPU, so the copy is packed (one memcpy unless padded)
+        let data = self.scaled.data(0);
+        let stride = self.scaled.stride(0);
+        let width = self.scaled.width() as usize;
+        let height = self.scaled.height() as usize;
+        let row_bytes = width * 4;
+
+        let mut pixels = self.pool.take(row_bytes * height);
+        if stride == row_bytes {
+            pixels.extend_from_slice(&data[..row_bytes * height]);
+        } else {
+            for i in 0..height {
+                let start = i * stride;
+                pixels.extend_from_slice(&data[start..start + row_bytes]);
+            }
         }
 
-        Ok(pixels)
+        Ok(VideoFrame::rgba(width as u32, height as u32, pixels))
     }
 
     pub fn width(&self) -> u32 {
@@ -142,13 +233,13 @@ impl VideoDecoder {
         self.decoder.height()
     }
 
-    pub fn seek(&mut self, timestamp_seconds: f64) -> Result<()> {
-        let target_ts =
-            (timestamp_seconds * self.time_base.1 as f64 / self.time_base.0 as f64) as i64;
+    pub fn seek(&mut self, time: Time) -> Result<()> {
+        // First tick at or after `time`, so a frame's exact time never rounds to the one before
+        let target_ts = time.ceil_to(self.pts_rate).value;
 
         println!(
-            "Decoder: Seeking to {:.2}s. Stream TimeBase: {:?}. Target Position (Ticks): {}",
-            timestamp_seconds, self.time_base, target_ts
+            "Decoder: Seeking to {}. Stream Ticks/s: {}. Target Position (Ticks): {}",
+            time, self.pts_rate, target_ts
         );
 
         // 1. Seek to Keyframe (Backward)
@@ -161,82 +252,136 @@ impl VideoDecoder {
         }
 
         self.decoder.flush();
+        self.pending.clear();
 
         // 2. Roll-Forward to Target
+        // The target is the last frame with PTS <= target_ts. Frames are only converted
+        // once decode_next_frame returns them, so skipping stays cheap.
         let mut frames_decoded = 0;
         let max_skip = 600; // Increased limit for larger GOP sizes (10s @ 60fps)
-        let mut decoded_frame = ffmpeg::util::frame::Video::empty();
+        let mut target: Option<ffmpeg::util::frame::Video> = None;
+        let mut found = false;
 
-        // We must loop manually because decode_next_frame clones/scales which is slow.
-        // We only want to decode until PTS >= target.
         'seek_loop: for (stream, packet) in self.context.packets() {
             if stream.index() == self.stream_index {
                 self.decoder.send_packet(&packet)?;
-                while self.decoder.receive_frame(&mut decoded_frame).is_ok() {
-                    let pts = decoded_frame.pts().unwrap_or(0);
-                    if pts >= target_ts {
-                        // Found our frame!
-                        // IMPORTANT: We must leave this frame in a state where decode_next_frame will use it?
-                        // No. decode_next_frame calls packets().
-                        // If we consume packets here, they are gone from the iterator.
-                        // BUT `self.context.packets()` creates a NEW iterator effectively wrapping `av_read_frame`.
-                        // `av_read_frame` reads sequentially from GLOBAL context state.
-                        // So if we read here, we advance the global state. Good.
-
-                        // BUT we have a `decoded_frame` right now that IS the target.
-                        // If we drop it, we lose the first frame user wants to see!
-                        // We can't "put it back".
-
-                        // Hack: Just process it and ignore the return?
-                        // Or better: Store it in a temporary buffer?
-
-                        // For this implementation:
-                        // We accept that `seek` might burn the *exact* target frame if we don't save it.
-                        // But wait! KinetixEngine calls `seek`, THEN calls `decode_next_frame` immediately.
-                        // If `seek` consumes the target frame, `decode_next_frame` will get target+1.
-                        // That is usually acceptable (1 frame off).
-                        // Unless target frame is the ONLY frame (end of stream).
-
-                        // Ideally: `seek` should return the frame if found.
-                        // But signature is `-> Result<()>`.
-
-                        // Let's just stop ONE frame early? No, pts checks are >=.
-                        // If we stop when `pts >= target_ts`, we hold that frame.
-                        break 'seek_loop;
-                    }
-
-                    frames_decoded += 1;
-                    if frames_decoded > max_skip {
-                        println!("Decoder: Seek timeout (max frames skipped)");
-                        break 'seek_loop;
-                    }
+                found = Self::roll_forward(&mut self.decoder, &mut self.pending, &mut target, target_ts, &mut frames_decoded, max_skip);
+                if found {
+                    break 'seek_loop;
                 }
             }
         }
 
+        if !found {
+            // End of stream: the decoder may still hold reordered frames
+            self.decoder.send_eof()?;
+            Self::roll_forward(&mut self.decoder, &mut self.pending, &mut target, target_ts, &mut frames_decoded, max_skip);
+        }
+
+        // Exact hit, timeout or end of stream: the newest frame at or before the target
+        if self.pending.is_empty() {
+            self.pending.extend(target);
+        }
+
         println!("Decoder: Seek Complete. Skipped {} frames.", frames_decoded);
         Ok(())
     }
+
+    /// Drains decoded frames while looking for the target of a seek. Returns true once
+    /// the search is over, with the frames to show queued in `pending` (or in `target`).
+    fn roll_forward(
+        decoder: &mut ffmpeg::decoder::Video,
+        pending: &mut VecDeque<ffmpeg::util::frame::Video>,
+        target: &mut Option<ffmpeg::util::frame::Video>,
+        target_ts: i64,
+        frames_decoded: &mut usize,
+        max_skip: usize,
+    ) -> bool {
+        loop {
+            let mut decoded_frame = ffmpeg::util::frame::Video::empty();
+            if decoder.receive_frame(&mut decoded_frame).is_err() {
+                return false; // Needs more packets
+            }
+            let pts = decoded_frame.pts().unwrap_or(0);
+
+            if pts > target_ts {
+                // Past the target: keep the frame before it (if any) and this one, in order
+                pending.extend(target.take());
+                pending.push_back(decoded_frame);
+                return true;
+            }
+
+            *target = Some(decoded_frame);
+            if pts == target_ts {
+                return true; // Exact hit
+            }
+
+            *frames_decoded += 1;
+            if *frames_decoded > max_skip {
+                println!("Decoder: Seek timeout (max frames skipped)");
+                return true;
+            }
+        }
+    }
+}
+
+// 2 s of numbered test pattern at 25 fps, keyframes every 12 frames and B-frames,
+// so seeks land mid-GOP and have to reorder. Unique per process, since test runs can overlap
+#[cfg(test)]
+pub(crate) fn generate_test_clip(name: &str) -> std::path::PathBuf {
+    let binary = super::export_utils::ffmpeg_binary();
+    let path = std::env::temp_dir().join(format!("kenichi_decoder_{}_{}.mp4", name, std::process::id()));
+    let status = std::process::Command::new(&binary)
+        .args(["-f", "lavfi", "-i", "testsrc=size=160x120:rate=25:duration=2"])
+        .args(["-c:v", "mpeg4", "-g", "12", "-bf", "2", "-pix_fmt", "yuv420p", "-y"])
+        .arg(&path)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status();
+    match status {
+        Ok(status) if status.success() => path,
+        _ => panic!("Decoder tests need the ffmpeg CLI to generate a clip (tried {})", binary),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::path::PathBuf;
+    use crate::engine::video_frame::PlaneLayout;
+
+    #[test]
+    #[ignore = "needs the ffmpeg CLI; run with --ignored"]
+    fn test_seek_returns_exact_frame() {
+        let path = generate_test_clip("seek");
+        let mut decoder = VideoDecoder::new(path.to_str().unwrap()).unwrap();
+        let fps = Rational::new(25, 1);
+
+        // Forward, backward, mid-GOP, on a keyframe, the first and the last frame
+        for frame in [17, 5, 30, 24, 0, 49] {
+            let target = Time::from_frames(frame, fps);
+            decoder.seek(target).unwrap();
+            let (video_frame, pts) = decoder.decode_next_frame().unwrap();
+            assert_eq!(pts, target, "seek to frame {}", frame);
+            assert_eq!((video_frame.width, video_frame.height), (160, 120));
+
+            // Playback continues with the following frame
+            if frame < 49 {
+                let (_, next) = decoder.decode_next_frame().unwrap();
+                assert_eq!(next, Time::from_frames(frame + 1, fps));
+            }
+        }
 
-    // Helper to get a test file path
-    fn get_test_asset() -> PathBuf {
-        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/assets/sample_1080p.mp4")
+        // Between two frames shows the earlier one
+        decoder.seek(Time::from_frames(21, Rational::new(50, 1))).unwrap(); // Frame 10.5
+        assert_eq!(decoder.decode_next_frame().unwrap().1, Time::from_frames(10, fps));
+
+        let _ = std::fs::remove_file(path);
     }
 
     #[test]
-    #[ignore = "Requires a video file at 'tests/assets/sample_1080p.mp4'"]
+    #[ignore = "needs the ffmpeg CLI; run with --ignored"]
     fn test_decoder_lifecycle() {
-        let path = get_test_asset();
-        if !path.exists() {
-            eprintln!("Test skipped: No asset found at {:?}", path);
-            return;
-        }
+        let path = generate_test_clip("lifecycle");
 
         let mut decoder =
             VideoDecoder::new(path.to_str().unwrap()).expect("Failed to create decoder");
@@ -244,24 +389,41 @@ mod tests {
         // Verify metadata
         assert!(decoder.width() > 0);
         assert!(decoder.height() > 0);
-        assert!(decoder.fps > 0.0);
+        assert!(decoder.frame_rate.num > 0);
 
-        // Decode first frame
-        let (pixels, timestamp) = decoder
+        // Decode first frame (YUV planes for the shader)
+        let (video_frame, timestamp) = decoder
             .decode_next_frame()
             .expect("Failed to decode first frame");
-        assert!(!pixels.is_empty());
-        assert_eq!(
-            pixels.len(),
-            (decoder.width() * decoder.height() * 4) as usize
-        );
+        assert_eq!(video_frame.layout, PlaneLayout::I420);
+        assert_eq!(video_frame.planes.len(), 3);
+        let luma = &video_frame.planes[0];
+        assert!(luma.stride >= decoder.width()); // FFmpeg's row padding is kept
+        assert_eq!(luma.data.len(), (luma.stride * decoder.height()) as usize);
         println!("Decoded frame at {}s", timestamp);
 
+        // Dropped frames give their planes back, the next frame reuses them
+        drop(video_frame);
+        assert_eq!(decoder.pool.free_count(), 3);
+        let (video_frame, _) = decoder.decode_next_frame().unwrap();
+        assert_eq!(decoder.pool.free_count(), 0);
+        drop(video_frame);
+
+        // Scaled output is RGBA
+        decoder.set_output_size(80, 60);
+        let (video_frame, _) = decoder
+            .decode_next_frame()
+            .expect("Failed to decode scaled frame");
+        assert_eq!(video_frame.rgba_pixels().map(<[u8]>::len), Some(80 * 60 * 4));
+
         // Test Seek
-        decoder.seek(2.0).expect("Seek failed");
+        let target = Time::from_seconds(1.0, decoder.frame_rate);
+        decoder.seek(target).expect("Seek failed");
         let (_, timestamp_after_seek) = decoder
             .decode_next_frame()
             .expect("Decode after seek failed");
-        assert!(timestamp_after_seek >= 2.0);
+        assert!(timestamp_after_seek >= target);
+
+        let _ = std::fs::remove_file(path);
     }
 }
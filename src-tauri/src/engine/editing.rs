@@ -0,0 +1,348 @@
+use super::history::Edit;
+use super::time::Time;
+use super::timeline::{Clip, TimelineManager};
+
+// Shortest clip an edit may leave behind
+pub const MIN_CLIP_FRAMES: i64 = 1;
+
+impl Clip {
+    /// Checks the clip only uses media that exists in its source.
+    pub fn validate_media_range(&self) -> Result<(), String> {
+        if self.duration.value < MIN_CLIP_FRAMES {
+            return Err(format!(
+                "Clip {} would be shorter than {} frame(s)",
+                self.name, MIN_CLIP_FRAMES
+            ));
+        }
+        if self.offset.value < 0 {
+            return Err(format!(
+                "Clip {} would start before its source media",
+                self.name
+            ));
+        }
+        if let Some(length) = self.source_duration {
+            if self.offset + self.duration > length {
+                return Err(format!(
+                    "Clip {} would run past the end of its source media ({})",
+                    self.name, length
+                ));
+            }
+        }
+        if self.start.value < 0 {
+            return Err(format!("Clip {} would start before 0s", self.name));
+        }
+        Ok(())
+    }
+}
+
+impl TimelineManager {
+    /// Splits a clip at a global time. Returns the ID of the new right-hand clip.
+    pub fn split_clip(&mut self, clip_id: &str, time: Time) -> Result<String, String> {
+        let before = self.editable_clip(clip_id)?;
+        let time = self.conform_time(time);
+        let min = Time::from_frames(MIN_CLIP_FRAMES, self.frame_rate());
+        if time < before.start + min || time > before.end() - min {
+            return Err(format!(
+                "Split point {} is outside clip {}",
+                time, before.name
+            ));
+        }
+
+        let mut left = before.clone();
+        let right = left.split_off(time);
+        let right_id = right.id.clone();
+
+        let edits = vec![
+            Edit::clip(clip_id.to_string(), Some(before), Some(left)),
+            Edit::clip(right_id.clone(), None, Some(right)),
+        ];
+        self.commit("Split Clip", edits);
+        Ok(right_id)
+    }
+
+    /// Moves the clip's in point to `time`, keeping the out point and the media under it.
+    pub fn trim_in(&mut self, clip_id: &str, time: Time) -> Result<(), String> {
+        let before = self.editable_clip(clip_id)?;
+        let delta = self.conform_time(time) - before.start;
+
+        let mut after = before.clone();
+        after.start += delta;
+        after.offset += delta;
+        after.duration -= delta;
+        after.validate_media_range()?;
+        self.check_free(&after)?;
+
+        self.commit(
+            "Trim In",
+            vec![Edit::clip(clip_id.to_string(), Some(before), Some(after))],
+        );
+        Ok(())
+    }
+
+    /// Moves the clip's out point to `time`.
+    pub fn trim_out(&mut self, clip_id: &str, time: Time) -> Result<(), String> {
+        let before = self.editable_clip(clip_id)?;
+
+        let mut after = before.clone();
+        after.duration = self.conform_time(time) - before.start;
+        after.validate_media_range()?;
+        self.check_free(&after)?;
+
+        self.commit(
+            "Trim Out",
+            vec![Edit::clip(clip_id.to_string(), Some(before), Some(after))],
+        );
+        Ok(())
+    }
+
+    /// Removes a clip and pulls later clips on its track left to close the gap.
+    pub fn ripple_delete(&mut self, clip_id: &str) -> Result<(), String> {
+        let removed = self.editable_clip(clip_id)?;
+
+        let mut edits: Vec<Edit> = self
+            .timeline
+            .clips
+            .values()
+            .filter(|c| c.track_id == removed.track_id && c.start >= removed.end())
+            .map(|c| {
+                let mut after = c.clone();
+                after.start -= removed.duration;
+                Edit::clip(c.id.clone(), Some(c.clone()), Some(after))
+            })
+            .collect();
+        edits.push(Edit::clip(clip_id.to_string(), Some(removed), None));
+
+        self.commit("Ripple Delete", edits);
+        Ok(())
+    }
+
+    /// Shifts which part of the source is shown, without moving the clip.
+    pub fn slip(&mut self, clip_id: &str, delta: Time) -> Result<(), String> {
+        let before = self.editable_clip(clip_id)?;
+        let delta = self.conform_time(delta);
+
+        let mut after = before.clone();
+        after.offset += delta;
+        after.validate_media_range()?;
+
+        self.commit(
+            "Slip Clip",
+            vec![Edit::clip(clip_id.to_string(), Some(before), Some(after))],
+        );
+        Ok(())
+    }
+
+    /// Moves a clip along its track. Adjacent neighbours are trimmed to follow it,
+    /// so the total length of the track stays the same.
+    pub fn slide(&mut self, clip_id: &str, delta: Time) -> Result<(), String> {
+        let before = self.editable_clip(clip_id)?;
+        let delta = self.conform_time(delta);
+
+        let mut after = before.clone();
+        after.start += delta;
+        after.validate_media_range()?;
+
+        let (previous, next) = self.neighbours(&before);
+        let mut edits = Vec::new();
+
+        // 1. Previous clip: extend/shorten its tail if it touches, otherwise it's a wall
+        if let Some(previous) = previous {
+            if previous.end() == before.start {
+                let mut trimmed = previous.clone();
+                trimmed.duration += delta;
+                trimmed.validate_media_range()?;
+                edits.push(Edit::clip(
+                    previous.id.clone(),
+                    Some(previous),
+                    Some(trimmed),
+                ));
+            } else if after.start < previous.end() {
+                return Err(format!(
+                    "Clip {} would overlap {}",
+                    after.name, previous.name
+                ));
+            }
+        }
+
+        // 2. Next clip: move its head the same way
+        if let Some(next) = next {
+            if next.start == before.end() {
+                let mut trimmed = next.clone();
+                trimmed.start += delta;
+                trimmed.offset += delta;
+                trimmed.duration -= delta;
+                trimmed.validate_media_range()?;
+                edits.push(Edit::clip(next.id.clone(), Some(next), Some(trimmed)));
+            } else if after.end() > next.start {
+                return Err(format!("Clip {} would overlap {}", after.name, next.name));
+            }
+        }
+
+        edits.push(Edit::clip(clip_id.to_string(), Some(before), Some(after)));
+        self.commit("Slide Clip", edits);
+        Ok(())
+    }
+
+    // Clip by ID, if its track isn't locked
+    fn editable_clip(&self, clip_id: &str) -> Result<Clip, String> {
+        let clip = self
+            .timeline
+            .clips
+            .get(clip_id)
+            .cloned()
+            .ok_or(format!("Clip not found: {}", clip_id))?;
+        self.check_unlocked(clip.track_id)?;
+        Ok(clip)
+    }
+
+    // Closest clips before and after `clip` on its track
+    fn neighbours(&self, clip: &Clip) -> (Option<Clip>, Option<Clip>) {
+        let on_track = || {
+            self.timeline
+                .clips
+                .values()
+                .filter(move |c| c.track_id == clip.track_id && c.id != clip.id)
+        };
+        let previous = on_track()
+            .filter(|c| c.end() <= clip.start)
+            .max_by_key(|c| c.end())
+            .cloned();
+        let next = on_track()
+            .filter(|c| c.start >= clip.end())
+            .min_by_key(|c| c.start)
+            .cloned();
+        (previous, next)
+    }
+
+    // Fails if the edited clip would cover another clip on its track.
+    // Magnetic tracks push the other clips along instead.
+    fn check_free(&self, clip: &Clip) -> Result<(), String> {
+        if self.is_magnetic(clip.track_id) {
+            return Ok(());
+        }
+        let blocker = self.timeline.clips.values().find(|c| {
+            c.track_id == clip.track_id && c.id != clip.id && c.overlaps(clip.start, clip.end())
+        });
+        match blocker {
+            Some(other) => Err(format!("Clip {} would overlap {}", clip.name, other.name)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::time::{Rational, DEFAULT_FRAME_RATE, MICROSECONDS};
+
+    fn t(seconds: f64) -> Time {
+        Time::from_seconds(seconds, DEFAULT_FRAME_RATE)
+    }
+
+    fn clip(id: &str, start: f64, duration: f64, offset: f64) -> Clip {
+        Clip {
+            id: id.into(),
+            name: id.into(),
+            start: t(start),
+            duration: t(duration),
+            offset: t(offset),
+            track_id: 1,
+            source_duration: Some(t(10.0)),
+            ..Default::default()
+        }
+    }
+
+    fn span(manager: &TimelineManager, id: &str) -> (f64, f64, f64) {
+        let c = &manager.timeline.clips[id];
+        (c.start.seconds(), c.duration.seconds(), c.offset.seconds())
+    }
+
+    fn manager_with(clips: &[Clip]) -> TimelineManager {
+        let mut manager = TimelineManager::new();
+        for clip in clips {
+            manager.add_clip(clip.clone()).unwrap();
+        }
+        manager
+    }
+
+    #[test]
+    fn test_split_and_trim() {
+        let mut manager = manager_with(&[clip("a", 2.0, 4.0, 1.0), clip("b", 6.0, 2.0, 0.0)]);
+
+        let right = manager.split_clip("a", t(3.0)).unwrap();
+        assert_eq!(span(&manager, "a"), (2.0, 1.0, 1.0));
+        assert_eq!(span(&manager, &right), (3.0, 3.0, 2.0));
+        assert!(manager.split_clip("a", t(5.0)).is_err()); // Outside the (now shorter) clip
+
+        manager.trim_in("a", t(1.5)).unwrap();
+        assert_eq!(span(&manager, "a"), (1.5, 1.5, 0.5));
+        assert!(manager.trim_in("a", t(0.0)).is_err()); // Before the source starts
+
+        assert!(manager.trim_out(&right, t(7.0)).is_err()); // Would cover "b"
+        manager.trim_out("b", t(9.0)).unwrap();
+        assert!(manager.trim_out("b", t(20.0)).is_err()); // Past the source media
+    }
+
+    #[test]
+    fn test_ripple_delete_closes_gap() {
+        let mut manager = manager_with(&[
+            clip("a", 0.0, 2.0, 0.0),
+            clip("b", 2.0, 3.0, 0.0),
+            clip("c", 6.0, 1.0, 0.0),
+        ]);
+        manager.ripple_delete("b").unwrap();
+        assert_eq!(span(&manager, "a"), (0.0, 2.0, 0.0));
+        assert_eq!(span(&manager, "c"), (3.0, 1.0, 0.0));
+        assert_eq!(manager.timeline.duration.seconds(), 4.0);
+    }
+
+    #[test]
+    fn test_slip_and_slide() {
+        let mut manager = manager_with(&[
+            clip("a", 0.0, 2.0, 0.0),
+            clip("b", 2.0, 2.0, 3.0),
+            clip("c", 4.0, 2.0, 3.0),
+        ]);
+
+        manager.slip("b", t(1.0)).unwrap();
+        assert_eq!(span(&manager, "b"), (2.0, 2.0, 4.0));
+        assert!(manager.slip("b", t(5.0)).is_err());
+
+        manager.slide("b", t(0.5)).unwrap();
+        assert_eq!(span(&manager, "a"), (0.0, 2.5, 0.0));
+        assert_eq!(span(&manager, "b"), (2.5, 2.0, 4.0));
+        assert_eq!(span(&manager, "c"), (4.5, 1.5, 3.5));
+        assert_eq!(manager.timeline.duration.seconds(), 6.0);
+
+        assert!(manager.slide("b", t(2.0)).is_err()); // "c" would vanish
+    }
+
+    #[test]
+    fn test_ntsc_edits_land_on_frames() {
+        let mut manager = TimelineManager::new();
+        let rate = Rational::new(24000, 1001);
+        manager.timeline.settings.frame_rate = rate;
+        let frame = |n: i64| Time::from_frames(n, rate);
+
+        // 1000 frames sent as float seconds, like the frontend does
+        manager
+            .add_clip(Clip {
+                id: "a".into(),
+                duration: Time::from_seconds(1000.0 * 1001.0 / 24000.0, MICROSECONDS),
+                track_id: 1,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(manager.timeline.clips["a"].duration, frame(1000));
+
+        // Split between frames lands on the nearest one; halves add up exactly
+        let right = manager
+            .split_clip("a", Time::from_seconds(10.0, Rational::new(1, 1)))
+            .unwrap();
+        let (left, right) = (&manager.timeline.clips["a"], &manager.timeline.clips[&right]);
+        assert_eq!(left.duration, frame(240)); // 10s = 239.76 frames
+        assert_eq!(right.offset, frame(240));
+        assert_eq!(left.duration + right.duration, frame(1000));
+        assert_eq!(manager.timeline.duration, frame(1000));
+    }
+}
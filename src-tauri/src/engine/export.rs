@@ -0,0 +1,343 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use super::audio::{AudioMixer, OUTPUT_CHANNELS};
+use super::export_utils::{ffmpeg_binary, JobKind, ProgressCallback, ProgressTracker};
+use super::settings::{ColorSpace, ProjectSettings};
+use super::time::{Rational, Time};
+use super::timeline::{Timeline, TimelineManager};
+use super::KinetixEngine;
+
+// Minimum wall time between progress events
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+// FFmpeg log lines kept for the error message when encoding fails
+const STDERR_TAIL_LINES: usize = 20;
+// Timeline audio is mixed at this rate and encoded as AAC
+const AUDIO_SAMPLE_RATE: u32 = 48_000;
+const AUDIO_BLOCK_FRAMES: usize = 48_000; // Samples per channel mixed per step
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportCodec {
+    #[default]
+    H264,
+    H265,
+}
+
+impl ExportCodec {
+    fn encoder_name(&self) -> &'static str {
+        match self {
+            ExportCodec::H264 => "libx264",
+            ExportCodec::H265 => "libx265",
+        }
+    }
+}
+
+/// Export options from the frontend. Size and frame rate default to the project settings.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportRequest {
+    pub output_path: String,
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    #[serde(default)]
+    pub fps: Option<Rational>, // A number (25) or an exact rate ("24000/1001")
+    #[serde(default)]
+    pub codec: ExportCodec,
+}
+
+impl ExportRequest {
+    /// Fills in everything not overridden from the project settings.
+    pub fn resolve(self, project: &ProjectSettings) -> ExportSettings {
+        ExportSettings {
+            output_path: self.output_path,
+            width: self.width.unwrap_or(project.width),
+            height: self.height.unwrap_or(project.height),
+            fps: self.fps.unwrap_or(project.frame_rate),
+            codec: self.codec,
+            pixel_aspect: project.pixel_aspect,
+            color_space: project.color_space,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ExportSettings {
+    pub output_path: String,
+    pub width: u32,
+    pub height: u32,
+    pub fps: Rational,
+    pub codec: ExportCodec,
+    pub pixel_aspect: Rational,
+    pub color_space: ColorSpace,
+}
+
+impl ExportSettings {
+    pub fn validate(&self) -> Result<()> {
+        if self.output_path.trim().is_empty() {
+            anyhow::bail!("Export path is empty");
+        }
+        // yuv420p needs even dimensions
+        if self.width == 0 || self.height == 0 || !self.width.is_multiple_of(2) || !self.height.is_multiple_of(2) {
+            anyhow::bail!(
+                "Export resolution must be non-zero and even, got {}x{}",
+                self.width,
+                self.height
+            );
+        }
+        if self.fps.num <= 0 || self.fps.den <= 0 {
+            anyhow::bail!("Export frame rate must be positive, got {}", self.fps);
+        }
+        Ok(())
+    }
+}
+
+/// Pipes raw RGBA frames into an FFmpeg process for encoding, muxed with the
+/// premixed timeline audio when there is any.
+struct FfmpegEncoder {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stderr_tail: Option<JoinHandle<String>>, // Drains FFmpeg's log so it can't block on a full pipe
+}
+
+impl FfmpegEncoder {
+    fn spawn(settings: &ExportSettings, audio: Option<&Path>) -> Result<Self> {
+        let (colorspace, primaries, trc) = settings.color_space.ffmpeg_tags();
+        let mut command = Command::new(ffmpeg_binary());
+        command
+            .arg("-hide_banner")
+            .arg("-loglevel").arg("error")
+            .arg("-f").arg("rawvideo")
+            .arg("-pix_fmt").arg("rgba")
+            .arg("-s").arg(format!("{}x{}", settings.width, settings.height))
+            .arg("-r").arg(settings.fps.to_string())
+            .arg("-i").arg("-"); // Frames arrive on stdin
+        if let Some(audio) = audio {
+            command
+                .arg("-f").arg("f32le")
+                .arg("-ar").arg(AUDIO_SAMPLE_RATE.to_string())
+                .arg("-ac").arg(OUTPUT_CHANNELS.to_string())
+                .arg("-i").arg(audio)
+                .arg("-c:a").arg("aac")
+                .arg("-b:a").arg("192k");
+        }
+        let mut child = command
+            .arg("-c:v").arg(settings.codec.encoder_name())
+            .arg("-vf").arg(format!("setsar={}", settings.pixel_aspect)) // Non-square pixels
+            .arg("-colorspace").arg(colorspace)
+            .arg("-color_primaries").arg(primaries)
+            .arg("-color_trc").arg(trc)
+            .arg("-pix_fmt").arg("yuv420p")
+            .arg("-preset").arg("medium")
+            .arg("-crf").arg("18")
+            .arg("-movflags").arg("+faststart")
+            .arg("-y")
+            .arg(&settings.output_path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let stdin = child.stdin.take();
+        let stderr_tail = child.stderr.take().map(|stderr| {
+            std::thread::spawn(move || {
+                let mut tail = VecDeque::with_capacity(STDERR_TAIL_LINES);
+                for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                    if tail.len() == STDERR_TAIL_LINES {
+                        tail.pop_front();
+                    }
+                    tail.push_back(line);
+                }
+                Vec::from(tail).join("\n")
+            })
+        });
+        Ok(Self { child, stdin, stderr_tail })
+    }
+
+    fn write_frame(&mut self, pixels: &[u8]) -> Result<()> {
+        let Some(stdin) = &mut self.stdin else {
+            anyhow::bail!("Encoder input already closed");
+        };
+        if let Err(e) = stdin.write_all(pixels) {
+            // A closed pipe means FFmpeg quit; its log says why
+            return Err(self.failure(&format!("FFmpeg stopped accepting frames ({})", e)));
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        drop(self.stdin.take()); // EOF tells FFmpeg to flush
+        let status = self.child.wait()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(self.failure(&format!("FFmpeg exited with {}", status)))
+        }
+    }
+
+    // `message` plus the end of FFmpeg's log, once the process has exited
+    fn failure(&mut self, message: &str) -> anyhow::Error {
+        drop(self.stdin.take());
+        let _ = self.child.wait();
+        let tail = self
+            .stderr_tail
+            .take()
+            .and_then(|reader| reader.join().ok())
+            .unwrap_or_default();
+        if tail.trim().is_empty() {
+            anyhow::anyhow!("{}", message)
+        } else {
+            anyhow::anyhow!("{}:\n{}", message, tail.trim_end())
+        }
+    }
+}
+
+/// Renders a timeline snapshot frame by frame and encodes it to a file.
+pub struct Exporter {
+    settings: ExportSettings,
+    timeline_manager: TimelineManager,
+    on_progress: Option<ProgressCallback>,
+}
+
+impl Exporter {
+    pub fn new(timeline: Timeline, request: ExportRequest) -> Result<Self> {
+        let settings = request.resolve(&timeline.settings);
+        settings.validate()?;
+        Ok(Self {
+            settings,
+            timeline_manager: TimelineManager::from_timeline(timeline),
+            on_progress: None,
+        })
+    }
+
+    pub fn with_progress(mut self, on_progress: ProgressCallback) -> Self {
+        self.on_progress = Some(on_progress);
+        self
+    }
+
+    pub fn total_frames(&self) -> u64 {
+        let duration = self.timeline_manager.timeline.duration;
+        duration.ceil_to(self.settings.fps).value.max(0) as u64
+    }
+
+    /// Blocking: renders and encodes every frame. Run off the async runtime.
+    /// The last progress event is either finished or failed, however it ends.
+    pub fn run(self) -> Result<String> {
+        let tracker = ProgressTracker::new(
+            self.settings.output_path.clone(),
+            JobKind::Export,
+            self.timeline_manager.timeline.duration.seconds(),
+        );
+        // A panic while rendering still ends the job for the frontend
+        let result = panic::catch_unwind(AssertUnwindSafe(|| self.encode(&tracker)))
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("Export crashed, see the log for details")));
+        if let (Err(e), Some(on_progress)) = (&result, &self.on_progress) {
+            on_progress(tracker.fail(e));
+        }
+        result.map(|()| self.settings.output_path)
+    }
+
+    fn encode(&self, tracker: &ProgressTracker) -> Result<()> {
+        let total_frames = self.total_frames();
+        if total_frames == 0 {
+            anyhow::bail!("Timeline is empty, nothing to export");
+        }
+
+        println!(
+            "Export: {} frames at {}x{} @ {} fps -> {}",
+            total_frames,
+            self.settings.width,
+            self.settings.height,
+            self.settings.fps,
+            self.settings.output_path
+        );
+
+        let audio = self.mix_audio()?;
+        let mut engine = self.open_engine()?;
+        let mut encoder = FfmpegEncoder::spawn(&self.settings, audio.as_ref().map(|file| file.0.as_path()))?;
+        let started = Instant::now();
+        let mut last_report = started;
+
+        for frame_index in 0..total_frames {
+            let time = Time::from_frames(frame_index as i64, self.settings.fps);
+            let pixels = engine.render_next_frame(time, self.settings.width, self.settings.height)?;
+            encoder.write_frame(&pixels)?;
+
+            if let Some(on_progress) = &self.on_progress {
+                if last_report.elapsed() >= PROGRESS_INTERVAL {
+                    let done = frame_index + 1;
+                    let fps = done as f64 / started.elapsed().as_secs_f64();
+                    let out_time = Time::from_frames(done as i64, self.settings.fps).seconds();
+                    on_progress(tracker.report(done, fps, out_time, false));
+                    last_report = Instant::now();
+                }
+            }
+        }
+
+        encoder.finish()?;
+
+        if let Some(on_progress) = &self.on_progress {
+            let fps = total_frames as f64 / started.elapsed().as_secs_f64();
+            let out_time = self.timeline_manager.timeline.duration.seconds();
+            on_progress(tracker.report(total_frames, fps, out_time, true));
+        }
+        println!("Export Complete: {}", self.settings.output_path);
+        Ok(())
+    }
+
+    /// Mixes the whole timeline into a raw float file for FFmpeg's second input,
+    /// through the same mixer as playback. None if no clip can carry audio.
+    fn mix_audio(&self) -> Result<Option<TempFile>> {
+        let rate = Rational::new(i64::from(AUDIO_SAMPLE_RATE), 1);
+        let duration = self.timeline_manager.timeline.duration;
+        if self
+            .timeline_manager
+            .get_audio_clips_in_range(Time::from_frames(0, rate), duration)
+            .is_empty()
+        {
+            return Ok(None);
+        }
+
+        let file = TempFile(std::env::temp_dir().join(format!("kenichi_export_{}.f32", uuid::Uuid::new_v4())));
+        let mut writer = BufWriter::new(std::fs::File::create(&file.0)?);
+        let mut mixer = AudioMixer::new(AUDIO_SAMPLE_RATE);
+        let total = duration.ceil_to(rate).value.max(0) as usize;
+        for first in (0..total).step_by(AUDIO_BLOCK_FRAMES) {
+            let frames = AUDIO_BLOCK_FRAMES.min(total - first);
+            let start = Time::from_frames(first as i64, rate).seconds();
+            for sample in mixer.mix(&self.timeline_manager, start, frames) {
+                writer.write_all(&sample.to_le_bytes())?;
+            }
+        }
+        writer.flush()?;
+        Ok(Some(file))
+    }
+
+    /// A headless engine on the snapshot, so export renders through the same
+    /// layers and shader as preview (YUV conversion, filtering, blending).
+    fn open_engine(&self) -> Result<KinetixEngine> {
+        let mut engine = KinetixEngine::new();
+        engine.timeline_manager = TimelineManager::from_timeline(self.timeline_manager.timeline.clone());
+        engine.frame_cache.set_budget(0); // Every frame is shown once
+        tauri::async_runtime::block_on(engine.init_headless())?;
+        Ok(engine)
+    }
+}
+
+// Removed when dropped, however the export ends
+struct TempFile(PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
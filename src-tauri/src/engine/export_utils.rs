@@ -1,5 +1,262 @@
 // FFmpeg utilities
-pub fn parse_progress(_line: &str) -> Option<f64> {
-    // TODO: Regex parsing
-    None
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+
+use super::time::{Rational, Time};
+
+/// Event name the frontend subscribes to for export and proxy progress.
+pub const JOB_PROGRESS_EVENT: &str = "job-progress";
+
+/// Receives progress updates from long-running jobs (export, proxy generation).
+pub type ProgressCallback = Arc<dyn Fn(JobProgress) + Send + Sync>;
+
+lazy_static! {
+    // `-progress` output: out_time_us=4040000 (out_time_ms is also microseconds)
+    static ref OUT_TIME_US_RE: Regex = Regex::new(r"^out_time_(?:us|ms)=(\d+)").unwrap();
+    // `-progress` output: out_time=00:00:04.040000, stats line: time=00:00:04.04
+    static ref TIME_RE: Regex = Regex::new(r"time=\s*(\d+):(\d{2}):(\d{2}(?:\.\d+)?)").unwrap();
+    static ref FRAME_RE: Regex = Regex::new(r"frame=\s*(\d+)").unwrap();
+    static ref FPS_RE: Regex = Regex::new(r"fps=\s*(\d+(?:\.\d+)?)").unwrap();
+    static ref PROGRESS_RE: Regex = Regex::new(r"^progress=(\w+)").unwrap();
+}
+
+/// Resolves the FFmpeg binary used for proxy generation and export.
+/// `KENICHI_FFMPEG` wins if set; then the sidecar for this platform (next to the
+/// executable once bundled, `src-tauri/bin/ffmpeg-<target triple>` in dev), then
+/// `ffmpeg` on PATH.
+pub fn ffmpeg_binary() -> String {
+    if let Some(path) = std::env::var_os("KENICHI_FFMPEG") {
+        return path.to_string_lossy().into_owned();
+    }
+
+    let suffix = std::env::consts::EXE_SUFFIX;
+    let sidecar = format!("ffmpeg-{}{}", env!("TAURI_ENV_TARGET_TRIPLE"), suffix);
+    let candidates = [
+        // Bundled: Tauri drops the target triple from sidecar names
+        std::env::current_exe()
+            .ok()
+            .and_then(|exe| Some(exe.parent()?.join(format!("ffmpeg{}", suffix)))),
+        // Dev, run from the repo root or from src-tauri (tests)
+        std::env::current_dir().ok().map(|cwd| cwd.join("src-tauri/bin").join(&sidecar)),
+        Some(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("bin").join(&sidecar)),
+    ];
+
+    candidates
+        .into_iter()
+        .flatten()
+        .find(|path| path.is_file())
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "ffmpeg".to_string()) // Fallback to PATH
+}
+
+/// Reads the container duration in seconds, if FFmpeg can open the file.
+pub fn probe_duration(path: &str) -> Option<f64> {
+    probe_length(path).map(Time::seconds)
+}
+
+/// Container duration, exact (in AV_TIME_BASE units).
+pub fn probe_length(path: &str) -> Option<Time> {
+    ffmpeg::init().ok()?;
+    let context = ffmpeg::format::input(&std::path::Path::new(path)).ok()?;
+    let duration = context.duration();
+    let time_base = Rational::new(i64::from(ffmpeg::ffi::AV_TIME_BASE), 1);
+    (duration > 0).then(|| Time::from_frames(duration, time_base))
+}
+
+/// Extracts the output position (seconds) from an FFmpeg progress or stats line.
+pub fn parse_progress(line: &str) -> Option<f64> {
+    let line = line.trim();
+
+    if let Some(caps) = OUT_TIME_US_RE.captures(line) {
+        let micros: f64 = caps[1].parse().ok()?;
+        return Some(micros / 1_000_000.0);
+    }
+
+    let caps = TIME_RE.captures(line)?;
+    let hours: f64 = caps[1].parse().ok()?;
+    let minutes: f64 = caps[2].parse().ok()?;
+    let seconds: f64 = caps[3].parse().ok()?;
+    Some(hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+/// One progress report as printed by FFmpeg.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FfmpegProgress {
+    pub frame: u64,
+    pub fps: f64,
+    pub out_time: f64, // Seconds
+    pub is_end: bool,
+}
+
+/// Accumulates `-progress` key=value lines into complete reports.
+#[derive(Default)]
+pub struct ProgressParser {
+    current: FfmpegProgress,
+}
+
+impl ProgressParser {
+    /// Feeds one line. Returns a report when a block ends (`progress=...`),
+    /// or immediately for a single-line stats report (`frame=... time=...`).
+    pub fn feed(&mut self, line: &str) -> Option<FfmpegProgress> {
+        let line = line.trim();
+        let is_stats_line = line.contains("frame=") && line.contains("time=");
+
+        if let Some(caps) = FRAME_RE.captures(line) {
+            self.current.frame = caps[1].parse().unwrap_or(self.current.frame);
+        }
+        if let Some(caps) = FPS_RE.captures(line) {
+            self.current.fps = caps[1].parse().unwrap_or(self.current.fps);
+        }
+        if let Some(time) = parse_progress(line) {
+            self.current.out_time = time;
+        }
+
+        if let Some(caps) = PROGRESS_RE.captures(line) {
+            self.current.is_end = &caps[1] == "end";
+            return Some(self.current);
+        }
+
+        is_stats_line.then_some(self.current)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobKind {
+    Export,
+    Proxy,
+}
+
+/// Progress event payload sent to the frontend.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobProgress {
+    pub id: String, // Output path for exports, source path for proxies
+    pub kind: JobKind,
+    pub frame: u64,
+    pub fps: f64,
+    pub percent: f64, // 0-100 of the job's duration
+    pub eta_seconds: Option<f64>,
+    pub finished: bool, // Last event of the job, whether it succeeded or not
+    pub error: Option<String>, // Why the job failed, on its last event
+}
+
+/// Turns raw positions into `JobProgress` with percent and ETA.
+pub struct ProgressTracker {
+    id: String,
+    kind: JobKind,
+    total_duration: f64, // Seconds of media the job covers
+    started: Instant,
+}
+
+impl ProgressTracker {
+    pub fn new(id: impl Into<String>, kind: JobKind, total_duration: f64) -> Self {
+        Self {
+            id: id.into(),
+            kind,
+            total_duration,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn report(&self, frame: u64, fps: f64, out_time: f64, finished: bool) -> JobProgress {
+        let fraction = if finished {
+            1.0
+        } else if self.total_duration > 0.0 {
+            (out_time / self.total_duration).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        // Linear extrapolation from wall time spent so far
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let eta_seconds = if finished {
+            Some(0.0)
+        } else if fraction > 0.0 {
+            Some(elapsed * (1.0 - fraction) / fraction)
+        } else {
+            None
+        };
+
+        JobProgress {
+            id: self.id.clone(),
+            kind: self.kind,
+            frame,
+            fps,
+            percent: fraction * 100.0,
+            eta_seconds,
+            finished,
+            error: None,
+        }
+    }
+
+    pub fn report_ffmpeg(&self, progress: &FfmpegProgress) -> JobProgress {
+        self.report(progress.frame, progress.fps, progress.out_time, progress.is_end)
+    }
+
+    /// The last event of a job that failed.
+    pub fn fail(&self, error: &anyhow::Error) -> JobProgress {
+        JobProgress {
+            id: self.id.clone(),
+            kind: self.kind,
+            frame: 0,
+            fps: 0.0,
+            percent: 0.0,
+            eta_seconds: None,
+            finished: true,
+            error: Some(format!("{:#}", error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_progress_formats() {
+        assert_eq!(parse_progress("out_time_us=4040000"), Some(4.04));
+        assert_eq!(parse_progress("out_time_ms=1500000"), Some(1.5));
+        assert_eq!(parse_progress("out_time=00:01:02.500000"), Some(62.5));
+        assert_eq!(
+            parse_progress("frame=  120 fps= 60 q=28.0 size=512kB time=01:00:00.00 bitrate=N/A"),
+            Some(3600.0)
+        );
+        assert_eq!(parse_progress("bitrate=1200.0kbits/s"), None);
+    }
+
+    #[test]
+    fn test_progress_parser_blocks() {
+        let mut parser = ProgressParser::default();
+        assert_eq!(parser.feed("frame=48"), None);
+        assert_eq!(parser.feed("fps=24.00"), None);
+        assert_eq!(parser.feed("out_time_us=2000000"), None);
+
+        let report = parser.feed("progress=continue").expect("block complete");
+        assert_eq!(report.frame, 48);
+        assert_eq!(report.fps, 24.0);
+        assert_eq!(report.out_time, 2.0);
+        assert!(!report.is_end);
+
+        assert!(parser.feed("progress=end").unwrap().is_end);
+    }
+
+    #[test]
+    fn test_tracker_reports_end_of_job() {
+        let tracker = ProgressTracker::new("out.mp4", JobKind::Export, 10.0);
+        let halfway = tracker.report(125, 25.0, 5.0, false);
+        assert_eq!((halfway.percent, halfway.finished), (50.0, false));
+        assert!(halfway.error.is_none());
+
+        let done = tracker.report(250, 25.0, 10.0, true);
+        assert_eq!((done.percent, done.eta_seconds), (100.0, Some(0.0)));
+
+        let failed = tracker.fail(&anyhow::anyhow!("FFmpeg exited with 1").context("Export failed"));
+        assert!(failed.finished);
+        assert_eq!(failed.error.as_deref(), Some("Export failed: FFmpeg exited with 1"));
+    }
 }
@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use super::time::Time;
+use super::video_frame::VideoFrame;
+
+// Default memory budget for decoded frames (about 60 frames of 1080p RGBA, 170 in YUV 4:2:0)
+pub const DEFAULT_BUDGET_BYTES: usize = 512 * 1024 * 1024;
+// Frames prefetched on each side of the playhead, budget permitting
+const PREFETCH_RADIUS: i64 = 12;
+
+/// A decoded frame (RGBA or YUV planes), shared between the cache and whoever shows it.
+#[derive(Clone, Debug)]
+pub struct CachedFrame {
+    pub frame: Arc<VideoFrame>,
+    pub pts: Time, // Media time of the frame
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct FrameKey {
+    path: String,
+    frame: i64, // Frame index at the source frame rate
+}
+
+struct Entry {
+    frame: CachedFrame,
+    last_used: u64, // Key into `lru`
+}
+
+/// Least-recently-used cache of decoded frames, keyed by source path and frame index.
+/// Seeks and frame steps over recently shown or prefetched ranges are served from
+/// here without touching the decoder.
+pub struct FrameCache {
+    budget: usize, // Bytes
+    used: usize,
+    clock: u64, // Bumped on every access
+    entries: HashMap<FrameKey, Entry>,
+    lru: BTreeMap<u64, FrameKey>, // Oldest first
+}
+
+impl FrameCache {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            used: 0,
+            clock: 0,
+            entries: HashMap::new(),
+            lru: BTreeMap::new(),
+        }
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Changes the memory budget, evicting the oldest frames if it shrank.
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.evict_to(budget);
+    }
+
+    pub fn used_bytes(&self) -> usize {
+        self.used
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the frame and marks it as recently used.
+    pub fn get(&mut self, path: &str, frame: i64) -> Option<CachedFrame> {
+        let key = FrameKey {
+            path: path.to_string(),
+            frame,
+        };
+        let tick = self.next_tick();
+        let entry = self.entries.get_mut(&key)?;
+        self.lru.remove(&entry.last_used);
+        entry.last_used = tick;
+        self.lru.insert(tick, key);
+        Some(entry.frame.clone())
+    }
+
+    /// Like `get`, without counting as a use (for prefetch bookkeeping).
+    pub fn contains(&self, path: &str, frame: i64) -> bool {
+        self.entries.contains_key(&FrameKey {
+            path: path.to_string(),
+            frame,
+        })
+    }
+
+    /// Stores a frame, evicting the least recently used ones to stay in budget.
+    /// Frames larger than the whole budget are not kept.
+    pub fn insert(&mut self, path: &str, frame: i64, cached: CachedFrame) {
+        let size = cached.frame.byte_size();
+        if size > self.budget {
+            return;
+        }
+
+        let key = FrameKey {
+            path: path.to_string(),
+            frame,
+        };
+        self.remove(&key);
+        self.evict_to(self.budget - size);
+
+        let tick = self.next_tick();
+        self.used += size;
+        self.lru.insert(tick, key.clone());
+        self.entries.insert(
+            key,
+            Entry {
+                frame: cached,
+                last_used: tick,
+            },
+        );
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.lru.clear();
+        self.used = 0;
+    }
+
+    /// How many frames each of `layers` layers may prefetch on either side of the
+    /// playhead. Half the budget at most, so recently shown frames survive.
+    pub fn prefetch_radius(&self, frame_bytes: usize, layers: usize) -> i64 {
+        let window = frame_bytes.max(1) * layers.max(1) * 2;
+        (((self.budget / 2) / window) as i64).min(PREFETCH_RADIUS)
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn remove(&mut self, key: &FrameKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.lru.remove(&entry.last_used);
+            self.used -= entry.frame.frame.byte_size();
+        }
+    }
+
+    fn evict_to(&mut self, limit: usize) {
+        while self.used > limit {
+            let Some((_, key)) = self.lru.pop_first() else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&key) {
+                self.used -= entry.frame.frame.byte_size();
+            }
+        }
+    }
+}
+
+impl Default for FrameCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_BUDGET_BYTES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::time::Rational;
+
+    fn frame(index: i64, bytes: usize) -> CachedFrame {
+        CachedFrame {
+            frame: Arc::new(VideoFrame::rgba(bytes as u32 / 4, 1, vec![index as u8; bytes].into())),
+            pts: Time::from_frames(index, Rational::new(25, 1)),
+        }
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = FrameCache::new(300);
+        for i in 0..3 {
+            cache.insert("a.mp4", i, frame(i, 100));
+        }
+        assert_eq!(cache.used_bytes(), 300);
+
+        // Touch frame 0, so frame 1 is the oldest when frame 3 arrives
+        assert_eq!(cache.get("a.mp4", 0).unwrap().frame.planes[0].data[0], 0);
+        cache.insert("a.mp4", 3, frame(3, 100));
+        assert!(cache.contains("a.mp4", 0));
+        assert!(!cache.contains("a.mp4", 1));
+        assert!(cache.contains("a.mp4", 3));
+        assert_eq!(cache.len(), 3);
+
+        // Same index in another file is a different frame
+        assert!(cache.get("b.mp4", 0).is_none());
+
+        // Replacing a frame doesn't count it twice; shrinking the budget evicts
+        cache.insert("a.mp4", 3, frame(3, 100));
+        assert_eq!(cache.used_bytes(), 300);
+        cache.set_budget(150);
+        assert_eq!(cache.len(), 1);
+        assert!(cache.contains("a.mp4", 3));
+
+        // Too big to ever fit
+        cache.insert("a.mp4", 9, frame(9, 200));
+        assert!(!cache.contains("a.mp4", 9));
+    }
+
+    #[test]
+    fn test_prefetch_radius_fits_budget() {
+        let hd = 1920 * 1080 * 4;
+        assert_eq!(FrameCache::default().prefetch_radius(hd, 1), 12);
+        assert_eq!(FrameCache::default().prefetch_radius(hd, 4), 4);
+        assert_eq!(FrameCache::new(hd).prefetch_radius(hd, 1), 0);
+    }
+}
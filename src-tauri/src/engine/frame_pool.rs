@@ -0,0 +1,194 @@
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, Weak};
+
+// Bytes of free buffers kept per pool: a couple of 4K 4:2:0 frames, enough for the
+// frames in flight to be recycled. The rest is freed, so buffers coming back from
+// cache eviction don't pile up outside the frame cache budget.
+const MAX_FREE_BYTES: usize = 32 * 1024 * 1024;
+
+#[derive(Default)]
+struct FreeList {
+    buffers: Vec<Vec<u8>>,
+    bytes: usize, // Capacity of `buffers`
+}
+
+/// Recycles plane buffers between decoded frames. A buffer goes back to its pool
+/// when the last frame using it is dropped (after upload, or when the frame cache
+/// evicts it), so steady playback stops allocating once the pipeline is full.
+#[derive(Clone, Default)]
+pub struct FramePool {
+    free: Arc<Mutex<FreeList>>,
+}
+
+impl FramePool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An empty buffer with room for `len` bytes, reusing the smallest free one that
+    /// fits and is at most twice as big (a luma buffer isn't spent on a chroma plane).
+    pub fn take(&self, len: usize) -> PooledBuffer {
+        let reused = {
+            let mut free = self.free.lock().unwrap();
+            let best = free
+                .buffers
+                .iter()
+                .enumerate()
+                .filter(|(_, buffer)| (len..=len.saturating_mul(2)).contains(&buffer.capacity()))
+                .min_by_key(|(_, buffer)| buffer.capacity())
+                .map(|(index, _)| index);
+            best.map(|index| {
+                let buffer = free.buffers.swap_remove(index);
+                free.bytes -= buffer.capacity();
+                buffer
+            })
+        };
+        let mut data = reused.unwrap_or_else(|| Vec::with_capacity(len));
+        data.clear();
+        PooledBuffer {
+            data,
+            pool: Arc::downgrade(&self.free),
+        }
+    }
+
+    /// Copies `bytes` into a pooled buffer.
+    pub fn copy(&self, bytes: &[u8]) -> PooledBuffer {
+        let mut buffer = self.take(bytes.len());
+        buffer.data.extend_from_slice(bytes);
+        buffer
+    }
+
+    /// Buffers waiting to be reused.
+    pub fn free_count(&self) -> usize {
+        self.free.lock().unwrap().buffers.len()
+    }
+
+    /// Bytes held by buffers waiting to be reused.
+    pub fn free_bytes(&self) -> usize {
+        self.free.lock().unwrap().bytes
+    }
+}
+
+/// Bytes of one plane, returned to the `FramePool` they came from when dropped.
+pub struct PooledBuffer {
+    data: Vec<u8>,
+    pool: Weak<Mutex<FreeList>>, // Dangling once the decoder is gone; the buffer is then freed
+}
+
+impl PooledBuffer {
+    pub fn extend_from_slice(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.data.capacity()
+    }
+}
+
+/// Not pooled (tests, one-off frames).
+impl From<Vec<u8>> for PooledBuffer {
+    fn from(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            pool: Weak::new(),
+        }
+    }
+}
+
+impl Deref for PooledBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl fmt::Debug for PooledBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PooledBuffer({} bytes)", self.data.len())
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        let Some(pool) = self.pool.upgrade() else {
+            return;
+        };
+        let mut free = pool.lock().unwrap();
+        let capacity = self.data.capacity();
+        if capacity > 0 && free.bytes + capacity <= MAX_FREE_BYTES {
+            free.bytes += capacity;
+            free.buffers.push(std::mem::take(&mut self.data));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffers_return_to_pool() {
+        let pool = FramePool::new();
+        let buffer = pool.copy(&[7; 100]);
+        let address = buffer.as_ptr();
+        assert_eq!(&buffer[..3], [7, 7, 7]);
+        assert_eq!(pool.free_count(), 0);
+
+        drop(buffer);
+        assert_eq!(pool.free_count(), 1);
+
+        // Same allocation again, emptied
+        let reused = pool.take(80);
+        assert_eq!(reused.as_ptr(), address);
+        assert!(reused.is_empty());
+        assert_eq!(pool.free_count(), 0);
+    }
+
+    #[test]
+    fn test_takes_smallest_buffer_that_fits() {
+        let pool = FramePool::new();
+        let (luma, chroma) = (pool.take(400), pool.take(100));
+        let chroma_address = chroma.as_ptr();
+        drop(luma);
+        drop(chroma);
+
+        assert_eq!(pool.take(100).as_ptr(), chroma_address); // And back again
+        let bigger = pool.take(1000); // Nothing fits: allocated
+        assert_eq!(pool.free_count(), 2);
+        drop(bigger);
+        assert_eq!(pool.free_count(), 3);
+        assert_eq!(pool.free_bytes(), 1500);
+    }
+
+    #[test]
+    fn test_keeps_small_requests_off_big_buffers() {
+        let pool = FramePool::new();
+        drop(pool.take(400));
+
+        // More than twice the size: allocated, the big buffer stays free
+        let small = pool.take(100);
+        assert_eq!(small.capacity(), 100);
+        assert_eq!(pool.free_count(), 1);
+        assert_eq!(pool.take(200).capacity(), 400); // Within twice: reused
+    }
+
+    #[test]
+    fn test_frees_past_byte_cap() {
+        let pool = FramePool::new();
+        let frames: Vec<PooledBuffer> = (0..4).map(|_| pool.take(MAX_FREE_BYTES / 3)).collect();
+        drop(frames);
+        assert_eq!(pool.free_count(), 3);
+        assert!(pool.free_bytes() <= MAX_FREE_BYTES);
+    }
+
+    #[test]
+    fn test_outlives_its_pool() {
+        let pool = FramePool::new();
+        let buffer = pool.copy(&[1, 2, 3]);
+        drop(pool);
+        assert_eq!(&buffer[..], [1, 2, 3]);
+        drop(buffer); // Freed, nowhere to return to
+    }
+}
@@ -0,0 +1,158 @@
+use serde::{Deserialize, Serialize};
+
+use super::settings::ProjectSettings;
+use super::timeline::Transform;
+
+/// How a clip's frame is laid onto the canvas when their shapes differ.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScaleMode {
+    /// Whole frame visible, background bars on the short sides (letterbox / pillarbox)
+    #[default]
+    Fit,
+    /// Canvas covered, the overhanging sides cropped (pan with `reframe`)
+    Fill,
+    /// Frame squeezed to the canvas, aspect ignored
+    Stretch,
+    /// Source pixels 1:1 on canvas pixels, centered
+    None,
+}
+
+// Largest reframe pan in either direction, in source widths/heights
+pub const MAX_REFRAME: f32 = 0.5;
+
+/// Where one layer lands on the canvas, before `Transform` position/scale/rotation.
+/// Drawn by `video.wgsl`, for preview and export alike.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Framing {
+    pub size: [f32; 2],      // Quad size in canvas widths/heights (1.0 = edge to edge)
+    pub uv_origin: [f32; 2], // Top-left of the sampled source window (0.0 - 1.0)
+    pub uv_size: [f32; 2],   // Sampled window size; below 1.0 where Fill crops
+}
+
+impl Framing {
+    /// Whole frame on the whole canvas.
+    pub const FULL: Framing = Framing {
+        size: [1.0, 1.0],
+        uv_origin: [0.0, 0.0],
+        uv_size: [1.0, 1.0],
+    };
+
+    /// Framing for a `source_width` x `source_height` frame (square pixels) on the
+    /// project canvas, using the clip's scale mode and reframe pan.
+    pub fn new(
+        transform: &Transform,
+        source_width: u32,
+        source_height: u32,
+        settings: &ProjectSettings,
+    ) -> Self {
+        if source_width == 0 || source_height == 0 || settings.height == 0 {
+            return Framing::FULL;
+        }
+
+        let source_aspect = source_width as f32 / source_height as f32;
+        // > 1: source is wider than the canvas
+        let ratio = source_aspect / settings.display_aspect();
+
+        match transform.scale_mode {
+            ScaleMode::Stretch => Framing::FULL,
+            ScaleMode::Fit => Framing {
+                size: if ratio > 1.0 {
+                    [1.0, 1.0 / ratio] // Letterbox
+                } else {
+                    [ratio, 1.0] // Pillarbox
+                },
+                ..Framing::FULL
+            },
+            ScaleMode::Fill => {
+                let uv_size = if ratio > 1.0 {
+                    [1.0 / ratio, 1.0] // Crop the sides
+                } else {
+                    [1.0, ratio] // Crop top and bottom
+                };
+                // Centered window, shifted by the pan but never past the frame edge
+                let origin = |axis: usize| {
+                    let slack = 1.0 - uv_size[axis];
+                    let pan = transform.reframe[axis].clamp(-MAX_REFRAME, MAX_REFRAME);
+                    (slack * 0.5 + pan).clamp(0.0, slack)
+                };
+                Framing {
+                    size: [1.0, 1.0],
+                    uv_origin: [origin(0), origin(1)],
+                    uv_size,
+                }
+            }
+            ScaleMode::None => {
+                // Canvas width in square pixels, so anamorphic canvases still match 1:1
+                let canvas_width = settings.width as f32 * settings.pixel_aspect.as_f64() as f32;
+                Framing {
+                    size: [
+                        source_width as f32 / canvas_width,
+                        source_height as f32 / settings.height as f32,
+                    ],
+                    ..Framing::FULL
+                }
+            }
+        }
+    }
+
+    /// Pixel size to decode the source at so the visible part maps 1:1 onto a
+    /// `canvas_width` x `canvas_height` output (one scale, in FFmpeg, for RGBA consumers).
+    pub fn decode_size(&self, canvas_width: u32, canvas_height: u32) -> (u32, u32) {
+        let axis = |canvas: u32, i: usize| {
+            ((canvas as f32 * self.size[i] / self.uv_size[i]).round() as u32).max(1)
+        };
+        (axis(canvas_width, 0), axis(canvas_height, 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn framing(mode: ScaleMode, reframe: [f32; 2], source: (u32, u32)) -> Framing {
+        let transform = Transform {
+            scale_mode: mode,
+            reframe,
+            ..Default::default()
+        };
+        Framing::new(&transform, source.0, source.1, &ProjectSettings::default())
+    }
+
+    fn assert_close(actual: [f32; 2], expected: [f32; 2]) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_vertical_clip_on_landscape_canvas() {
+        // 9:16 phone footage on a 16:9 canvas
+        let fit = framing(ScaleMode::Fit, [0.0, 0.0], (1080, 1920));
+        assert_close(fit.size, [0.31640625, 1.0]);
+        assert_eq!(fit.uv_size, [1.0, 1.0]);
+
+        let fill = framing(ScaleMode::Fill, [0.0, 0.0], (1080, 1920));
+        assert_eq!(fill.size, [1.0, 1.0]);
+        assert_close(fill.uv_size, [1.0, 0.31640625]);
+        assert_close(fill.uv_origin, [0.0, 0.3417969]);
+        assert_eq!(fill.decode_size(1920, 1080), (1920, 3413));
+
+        // Panned to the top, then past it (clamped at the edge)
+        assert_close(framing(ScaleMode::Fill, [0.0, -0.2], (1080, 1920)).uv_origin, [0.0, 0.1417969]);
+        assert_close(framing(ScaleMode::Fill, [0.3, -0.5], (1080, 1920)).uv_origin, [0.0, 0.0]);
+
+        assert_eq!(framing(ScaleMode::Stretch, [0.0, 0.0], (1080, 1920)), Framing::FULL);
+    }
+
+    #[test]
+    fn test_native_size() {
+        let none = framing(ScaleMode::None, [0.0, 0.0], (1280, 720));
+        assert_close(none.size, [2.0 / 3.0, 2.0 / 3.0]);
+        assert_eq!(none.decode_size(1920, 1080), (1280, 720));
+
+        // Same aspect as the canvas: every mode but None fills it exactly
+        assert_eq!(framing(ScaleMode::Fit, [0.0, 0.0], (3840, 2160)), Framing::FULL);
+        assert_eq!(framing(ScaleMode::Fill, [0.2, 0.0], (3840, 2160)), Framing::FULL);
+    }
+}
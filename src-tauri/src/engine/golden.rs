@@ -0,0 +1,496 @@
+// Golden-image harness: synthesizes test media with known patterns, renders
+// timeline frames offscreen and compares them against the reference PNGs
+// checked in under `tests/golden/`. A missing reference fails the test; set
+// KENICHI_BLESS=1 to write references after an intended rendering change.
+// Failures leave the actual frame and a diff image in `target/golden/`.
+//
+// The rendering tests need the ffmpeg CLI (KENICHI_FFMPEG or PATH) and a GPU
+// adapter, so they are #[ignore]d and run with `cargo test -- --ignored`;
+// once opted in, missing prerequisites fail instead of passing silently.
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use super::export_utils::ffmpeg_binary;
+use super::settings::ProjectSettings;
+use super::time::{Rational, Time};
+use super::timeline::{Clip, Transform};
+use super::KinetixEngine;
+
+// 75% SMPTE bars: white, yellow, cyan, green, magenta, red, blue, black
+const BARS: [[u8; 3]; 8] = [
+    [191, 191, 191],
+    [191, 191, 0],
+    [0, 191, 191],
+    [0, 191, 0],
+    [191, 0, 191],
+    [191, 0, 0],
+    [0, 0, 191],
+    [0, 0, 0],
+];
+// Blocks in the burn-in row (frame numbers up to 1023)
+const FRAME_BITS: u32 = 10;
+
+/// How far a render may drift from its reference (GPU rasterizers differ at edges).
+#[derive(Clone, Copy, Debug)]
+pub struct Tolerance {
+    pub per_channel: u8,  // Largest difference that still counts as a match
+    pub max_mismatch: f64, // Fraction of pixels allowed past `per_channel`
+}
+
+pub const DEFAULT_TOLERANCE: Tolerance = Tolerance {
+    per_channel: 2,
+    max_mismatch: 0.005,
+};
+
+// --- Test Media ---
+
+/// Frame `frame` of the test pattern: color bars over the top three quarters,
+/// the frame number in binary below (white = 1, most significant bit left).
+pub fn pattern(frame: u32, width: u32, height: u32) -> Vec<u8> {
+    let bars_height = height * 3 / 4;
+    let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let [r, g, b] = if y < bars_height {
+                BARS[(x * BARS.len() as u32 / width) as usize]
+            } else {
+                let bit = FRAME_BITS - 1 - x * FRAME_BITS / width;
+                if (frame >> bit) & 1 == 1 { [255, 255, 255] } else { [0, 0, 0] }
+            };
+            pixels.extend_from_slice(&[r, g, b, 255]);
+        }
+    }
+    pixels
+}
+
+/// Reads the burn-in of `pattern` back from a frame showing it full canvas.
+pub fn read_frame_number(pixels: &[u8], width: u32, height: u32) -> u32 {
+    let y = height * 7 / 8;
+    (0..FRAME_BITS).fold(0, |number, block| {
+        let x = (2 * block + 1) * width / (2 * FRAME_BITS); // Block center
+        let red = pixels[((y * width + x) * 4) as usize];
+        (number << 1) | u32::from(red > 127)
+    })
+}
+
+/// Center color of bar `index`, as `pattern` draws it.
+pub fn bar_color(pixels: &[u8], width: u32, height: u32, index: usize) -> [u8; 3] {
+    let x = (2 * index as u32 + 1) * width / (2 * BARS.len() as u32);
+    let y = height * 3 / 8;
+    let i = ((y * width + x) * 4) as usize;
+    [pixels[i], pixels[i + 1], pixels[i + 2]]
+}
+
+/// Encodes `frames` frames of `pattern` with FFV1 (intra only) into a temp file.
+/// `bgr0` keeps decoded pixels exact; `yuv420p` exercises the YUV path, with
+/// FFmpeg's default BT.601 limited range conversion. The file is unique per process,
+/// so overlapping test runs don't overwrite each other. None if ffmpeg is not available.
+pub fn synthesize_clip(name: &str, width: u32, height: u32, fps: i32, frames: u32, pix_fmt: &str) -> Option<PathBuf> {
+    let path = std::env::temp_dir().join(format!("kenichi_golden_{}_{}.mkv", name, std::process::id()));
+    let mut child = Command::new(ffmpeg_binary())
+        .args(["-f", "rawvideo", "-pix_fmt", "rgba"])
+        .args(["-s", &format!("{}x{}", width, height), "-r", &fps.to_string()])
+        .args(["-i", "-", "-c:v", "ffv1", "-pix_fmt", pix_fmt, "-y"])
+        .arg(&path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    let mut stdin = child.stdin.take()?;
+    let written = (0..frames).try_for_each(|frame| stdin.write_all(&pattern(frame, width, height)));
+    drop(stdin); // EOF ends the encode
+    let status = child.wait().ok()?;
+    (written.is_ok() && status.success()).then_some(path)
+}
+
+/// A clip covering `frames` frames of `path` from the timeline start.
+pub fn clip(id: &str, path: &Path, track_id: i32, frames: i64, rate: Rational) -> Clip {
+    Clip {
+        id: id.into(),
+        path: path.to_string_lossy().into_owned(),
+        name: id.into(),
+        start: Time::from_frames(0, rate),
+        duration: Time::from_frames(frames, rate),
+        offset: Time::from_frames(0, rate),
+        track_id,
+        ..Default::default()
+    }
+}
+
+// --- Rendering ---
+
+/// An engine on a headless GPU with `settings`. Panics if no adapter is available.
+pub fn headless_engine(settings: ProjectSettings) -> KinetixEngine {
+    let mut engine = KinetixEngine::new();
+    engine.timeline_manager.set_settings(settings).unwrap();
+    if let Err(e) = tauri::async_runtime::block_on(engine.init_headless()) {
+        panic!("Golden tests need a GPU adapter (a software one will do): {}", e);
+    }
+    engine
+}
+
+/// `synthesize_clip`, failing the test if ffmpeg is missing.
+pub fn require_clip(name: &str, width: u32, height: u32, fps: i32, frames: u32, pix_fmt: &str) -> PathBuf {
+    synthesize_clip(name, width, height, fps, frames, pix_fmt).unwrap_or_else(|| {
+        panic!("Golden tests need the ffmpeg CLI to synthesize media (tried {})", ffmpeg_binary())
+    })
+}
+
+// --- Comparison ---
+
+fn reference_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.png", name))
+}
+
+fn artifact_path(name: &str, kind: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/golden").join(format!("{}.{}.png", name, kind))
+}
+
+pub fn write_png(path: &Path, pixels: &[u8], width: u32, height: u32) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(pixels)?;
+    Ok(())
+}
+
+// RGBA8 pixels and size
+pub fn read_png(path: &Path) -> Result<(Vec<u8>, u32, u32)> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut reader = png::Decoder::new(file).read_info()?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels)?;
+    if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+        anyhow::bail!("{} is not 8-bit RGBA", path.display());
+    }
+    pixels.truncate(info.buffer_size());
+    Ok((pixels, info.width, info.height))
+}
+
+/// Pixels past `per_channel` in red (brighter = further off) over a dimmed
+/// grayscale of the expected frame. Returns the image and the mismatch count.
+pub fn diff_image(actual: &[u8], expected: &[u8], per_channel: u8) -> (Vec<u8>, usize) {
+    let mut mismatched = 0;
+    let diff = actual
+        .chunks_exact(4)
+        .zip(expected.chunks_exact(4))
+        .flat_map(|(a, e)| {
+            let delta = a.iter().zip(e).map(|(a, e)| a.abs_diff(*e)).max().unwrap_or(0);
+            if delta > per_channel {
+                mismatched += 1;
+                [128u8.saturating_add(delta / 2), 0, 0, 255]
+            } else {
+                let gray = ((u32::from(e[0]) + u32::from(e[1]) + u32::from(e[2])) / 12) as u8;
+                [gray, gray, gray, 255]
+            }
+        })
+        .collect();
+    (diff, mismatched)
+}
+
+/// Compares a render against `tests/golden/<name>.png` and panics with the
+/// paths of the actual frame and the diff image if it drifted too far, or if
+/// there is no reference. KENICHI_BLESS writes the render as the reference.
+pub fn assert_golden(name: &str, pixels: &[u8], width: u32, height: u32, tolerance: Tolerance) {
+    let reference = reference_path(name);
+    if std::env::var_os("KENICHI_BLESS").is_some() {
+        write_png(&reference, pixels, width, height).unwrap();
+        eprintln!("Golden: Wrote reference {}", reference.display());
+        return;
+    }
+
+    let actual_path = artifact_path(name, "actual");
+    if !reference.exists() {
+        write_png(&actual_path, pixels, width, height).unwrap();
+        panic!(
+            "{}: no reference at {} (actual frame: {}); run with KENICHI_BLESS=1 and check it in",
+            name,
+            reference.display(),
+            actual_path.display()
+        );
+    }
+    let (expected, expected_width, expected_height) = read_png(&reference).unwrap();
+    if (expected_width, expected_height) != (width, height) {
+        write_png(&actual_path, pixels, width, height).unwrap();
+        panic!(
+            "{}: rendered {}x{}, reference is {}x{} (actual frame: {})",
+            name,
+            width,
+            height,
+            expected_width,
+            expected_height,
+            actual_path.display()
+        );
+    }
+
+    let (diff, mismatched) = diff_image(pixels, &expected, tolerance.per_channel);
+    let allowed = (tolerance.max_mismatch * f64::from(width * height)) as usize;
+    if mismatched > allowed {
+        let diff_path = artifact_path(name, "diff");
+        write_png(&actual_path, pixels, width, height).unwrap();
+        write_png(&diff_path, &diff, width, height).unwrap();
+        panic!(
+            "{}: {} pixels differ by more than {} (allowed {})\n  expected: {}\n  actual:   {}\n  diff:     {}",
+            name,
+            mismatched,
+            tolerance.per_channel,
+            allowed,
+            reference.display(),
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    use crate::engine::export::{ExportCodec, ExportRequest, Exporter};
+    use crate::engine::timeline::{TimelineManager, TrackKind};
+
+    const WIDTH: u32 = 320;
+    const HEIGHT: u32 = 240;
+    const FRAMES: u32 = 50;
+
+    // Chroma at color edges depends on the encoder's downsampling filter
+    const YUV_TOLERANCE: Tolerance = Tolerance {
+        per_channel: 3,
+        max_mismatch: 0.05,
+    };
+
+    fn fps() -> Rational {
+        Rational::new(25, 1)
+    }
+
+    fn settings() -> ProjectSettings {
+        ProjectSettings {
+            width: WIDTH,
+            height: HEIGHT,
+            frame_rate: fps(),
+            background_color: [0.2, 0.4, 0.6, 1.0],
+            ..Default::default()
+        }
+    }
+
+    fn assert_near(actual: [u8; 3], expected: [u8; 3], what: &str) {
+        let delta = actual.iter().zip(expected).map(|(a, e)| a.abs_diff(e)).max().unwrap();
+        assert!(delta <= DEFAULT_TOLERANCE.per_channel, "{}: {:?}, expected {:?}", what, actual, expected);
+    }
+
+    fn pixel(pixels: &[u8], x: u32, y: u32) -> [u8; 3] {
+        let i = ((y * WIDTH + x) * 4) as usize;
+        [pixels[i], pixels[i + 1], pixels[i + 2]]
+    }
+
+    #[test]
+    fn test_pattern_round_trips() {
+        for frame in [0, 1, 17, 512, 1023] {
+            assert_eq!(read_frame_number(&pattern(frame, WIDTH, HEIGHT), WIDTH, HEIGHT), frame);
+        }
+        let bars = pattern(0, WIDTH, HEIGHT);
+        for (index, color) in BARS.iter().enumerate() {
+            assert_eq!(bar_color(&bars, WIDTH, HEIGHT, index), *color);
+        }
+    }
+
+    #[test]
+    fn test_diff_counts_pixels_past_tolerance() {
+        let expected = pattern(0, 4, 4);
+        let mut actual = expected.clone();
+        actual[0] = actual[0].wrapping_add(2); // Within tolerance
+        actual[4] = actual[4].wrapping_add(40);
+        actual[9] = actual[9].wrapping_sub(3);
+
+        let (diff, mismatched) = diff_image(&actual, &expected, 2);
+        assert_eq!(mismatched, 2);
+        assert_eq!(diff.len(), expected.len());
+        assert_eq!(diff[4..8], [148, 0, 0, 255]);
+    }
+
+    #[test]
+    #[ignore = "needs the ffmpeg CLI and a GPU adapter; run with --ignored"]
+    fn test_renders_exact_frames() {
+        let path = require_clip("bars", WIDTH, HEIGHT, 25, FRAMES, "bgr0");
+        let mut engine = headless_engine(settings());
+        engine.timeline_manager.add_clip(clip("bars", &path, 1, FRAMES.into(), fps())).unwrap();
+
+        // Forward, backward, across the one second catch-up distance, the last frame
+        for frame in [0, 17, 5, 42, 49] {
+            let pixels = engine.render_frame_at(Time::from_frames(frame.into(), fps())).unwrap();
+            assert_eq!(read_frame_number(&pixels, WIDTH, HEIGHT), frame, "render at frame {}", frame);
+            for (index, color) in BARS.iter().enumerate() {
+                assert_near(bar_color(&pixels, WIDTH, HEIGHT, index), *color, &format!("bar {}", index));
+            }
+            if frame == 17 {
+                assert_golden("color_bars_17", &pixels, WIDTH, HEIGHT, DEFAULT_TOLERANCE);
+            }
+        }
+
+        drop(engine);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    #[ignore = "needs the ffmpeg CLI and a GPU adapter; run with --ignored"]
+    fn test_converts_yuv_on_gpu() {
+        let path = require_clip("yuv", WIDTH, HEIGHT, 25, 10, "yuv420p");
+        let mut engine = headless_engine(settings());
+        engine.timeline_manager.add_clip(clip("yuv", &path, 1, 10, fps())).unwrap();
+
+        // 8-bit limited range YUV loses a little on the way there and back
+        let pixels = engine.render_frame_at(Time::from_frames(7, fps())).unwrap();
+        assert_eq!(read_frame_number(&pixels, WIDTH, HEIGHT), 7);
+        for (index, color) in BARS.iter().enumerate() {
+            let actual = bar_color(&pixels, WIDTH, HEIGHT, index);
+            let delta = actual.iter().zip(color).map(|(a, e)| a.abs_diff(*e)).max().unwrap();
+            assert!(delta <= 4, "bar {}: {:?}, expected {:?}", index, actual, color);
+        }
+        assert_golden("yuv420p_bars", &pixels, WIDTH, HEIGHT, YUV_TOLERANCE);
+
+        drop(engine);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    #[ignore = "needs the ffmpeg CLI and a GPU adapter; run with --ignored"]
+    fn test_fit_pillarboxes_portrait_clip() {
+        let path = require_clip("portrait", 120, 240, 25, 5, "bgr0");
+        let mut engine = headless_engine(settings());
+        engine.timeline_manager.add_clip(clip("portrait", &path, 1, 5, fps())).unwrap();
+
+        let pixels = engine.render_frame_at(Time::from_frames(3, fps())).unwrap();
+        // 120 px wide, centered: background on both sides
+        assert_near(pixel(&pixels, 10, 120), [51, 102, 153], "left bar");
+        assert_near(pixel(&pixels, WIDTH - 10, 120), [51, 102, 153], "right bar");
+        assert_near(pixel(&pixels, 105, 90), BARS[0], "first bar");
+        assert_golden("fit_pillarbox", &pixels, WIDTH, HEIGHT, DEFAULT_TOLERANCE);
+
+        drop(engine);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    #[ignore = "needs the ffmpeg CLI and a GPU adapter; run with --ignored"]
+    fn test_composites_layers_in_track_order() {
+        let path = require_clip("layers", WIDTH, HEIGHT, 25, 10, "bgr0");
+        let mut engine = headless_engine(settings());
+        let mut top = clip("top", &path, 2, 10, fps());
+        top.offset = Time::from_frames(4, fps()); // Different burn-in from the bottom layer
+        top.transform = Transform {
+            position: [0.25, 0.25],
+            scale: 0.5,
+            opacity: 0.5,
+            ..Default::default()
+        };
+        engine.timeline_manager.add_clip(clip("bottom", &path, 1, 10, fps())).unwrap();
+        engine.timeline_manager.add_clip(top).unwrap();
+
+        let pixels = engine.render_frame_at(Time::from_frames(2, fps())).unwrap();
+        // Top left quarter is the bottom layer alone
+        assert_near(pixel(&pixels, 20, 40), BARS[0], "bottom layer");
+        // White bar of the top layer at half opacity over the bottom layer's magenta bar
+        assert_near(pixel(&pixels, 170, 130), [191, 96, 191], "blended");
+        assert_golden("layers_opacity", &pixels, WIDTH, HEIGHT, DEFAULT_TOLERANCE);
+
+        drop(engine);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    #[ignore = "needs the ffmpeg CLI and a GPU adapter; run with --ignored"]
+    fn test_missing_media_fails_without_waiting() {
+        let missing = std::env::temp_dir().join("kenichi_golden_missing.mkv");
+        let _ = std::fs::remove_file(&missing);
+        let mut engine = headless_engine(settings());
+        engine.timeline_manager.add_clip(clip("missing", &missing, 1, 10, fps())).unwrap();
+
+        let started = Instant::now();
+        let error = engine.render_frame_at(Time::from_frames(3, fps())).unwrap_err();
+        assert!(error.to_string().contains("kenichi_golden_missing.mkv"), "{}", error);
+
+        // Export stops at the first frame instead of timing out on every one
+        let output = std::env::temp_dir().join(format!("kenichi_golden_missing_{}.mp4", std::process::id()));
+        let request = ExportRequest {
+            output_path: output.to_string_lossy().into_owned(),
+            width: None,
+            height: None,
+            fps: None,
+            codec: ExportCodec::H264,
+        };
+        let exporter = Exporter::new(engine.timeline_manager.timeline.clone(), request).unwrap();
+        let error = exporter.run().unwrap_err();
+        assert!(error.to_string().contains("kenichi_golden_missing.mkv"), "{}", error);
+        assert!(started.elapsed() < Duration::from_secs(2), "took {:?}", started.elapsed());
+
+        let _ = std::fs::remove_file(output);
+    }
+
+    #[test]
+    #[ignore = "needs the ffmpeg CLI and a GPU adapter; run with --ignored"]
+    fn test_export_muxes_timeline_audio() {
+        let path = require_clip("export", WIDTH, HEIGHT, 25, 10, "bgr0");
+        let tone = std::env::temp_dir().join(format!("kenichi_golden_tone_{}.wav", std::process::id()));
+        let status = Command::new(ffmpeg_binary())
+            .args(["-f", "lavfi", "-i", "sine=frequency=440:duration=0.4", "-y"])
+            .arg(&tone)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success(), "Golden tests need the ffmpeg CLI to synthesize a tone");
+
+        let mut timeline = TimelineManager::new();
+        timeline.set_settings(settings()).unwrap();
+        let music = timeline.add_track("Music", TrackKind::Audio);
+        timeline.add_clip(clip("picture", &path, 1, 10, fps())).unwrap();
+        timeline.add_clip(clip("music", &tone, music.id, 10, fps())).unwrap();
+
+        let output = std::env::temp_dir().join(format!("kenichi_golden_export_{}.mp4", std::process::id()));
+        let request = ExportRequest {
+            output_path: output.to_string_lossy().into_owned(),
+            width: None,
+            height: None,
+            fps: None,
+            codec: ExportCodec::H264,
+        };
+        Exporter::new(timeline.timeline, request).unwrap().run().unwrap();
+
+        ffmpeg::init().unwrap();
+        let input = ffmpeg::format::input(&output).unwrap();
+        assert!(input.streams().best(ffmpeg::media::Type::Video).is_some());
+        assert!(input.streams().best(ffmpeg::media::Type::Audio).is_some());
+
+        drop(input);
+        for file in [path, tone, output] {
+            let _ = std::fs::remove_file(file);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs the ffmpeg CLI and a GPU adapter; run with --ignored"]
+    fn test_clip_past_media_end_does_not_wait() {
+        let path = require_clip("short", WIDTH, HEIGHT, 25, 5, "bgr0");
+        let mut engine = headless_engine(settings());
+        engine.timeline_manager.add_clip(clip("short", &path, 1, 8, fps())).unwrap();
+
+        // Frames 5 to 7 have no media; the stream ends instead
+        let started = Instant::now();
+        engine.render_frame_at(Time::from_frames(7, fps())).unwrap();
+        assert!(started.elapsed() < Duration::from_secs(2), "took {:?}", started.elapsed());
+
+        drop(engine);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,350 @@
+use std::collections::VecDeque;
+
+use super::settings::ProjectSettings;
+use super::timeline::{Clip, Marker, Timeline, Track};
+
+// Limits for the undo stack. Whichever is hit first drops the oldest steps.
+const MAX_UNDO_STEPS: usize = 200;
+const MAX_HISTORY_BYTES: usize = 32 * 1024 * 1024;
+
+/// A reversible change to the timeline.
+/// Stores the state before and after, so undo/redo just swap them in.
+#[derive(Clone, Debug)]
+pub enum Edit {
+    /// `None` before = clip was added, `None` after = clip was removed
+    Clip {
+        id: String,
+        before: Option<Box<Clip>>,
+        after: Option<Box<Clip>>,
+    },
+    /// Same for tracks; removing a track also records removing its clips
+    Track {
+        id: i32,
+        before: Option<Track>,
+        after: Option<Track>,
+    },
+    Marker {
+        id: String,
+        before: Option<Marker>,
+        after: Option<Marker>,
+    },
+    Settings {
+        before: ProjectSettings,
+        after: ProjectSettings,
+    },
+}
+
+impl Edit {
+    pub fn clip(id: String, before: Option<Clip>, after: Option<Clip>) -> Self {
+        Edit::Clip {
+            id,
+            before: before.map(Box::new),
+            after: after.map(Box::new),
+        }
+    }
+
+    pub fn apply(&self, timeline: &mut Timeline) {
+        match self {
+            Edit::Clip { id, after, .. } => set_clip(timeline, id, after),
+            Edit::Track { id, after, .. } => set_track(timeline, *id, after),
+            Edit::Marker { id, after, .. } => set_marker(timeline, id, after),
+            Edit::Settings { after, .. } => timeline.settings = *after,
+        }
+    }
+
+    pub fn revert(&self, timeline: &mut Timeline) {
+        match self {
+            Edit::Clip { id, before, .. } => set_clip(timeline, id, before),
+            Edit::Track { id, before, .. } => set_track(timeline, *id, before),
+            Edit::Marker { id, before, .. } => set_marker(timeline, id, before),
+            Edit::Settings { before, .. } => timeline.settings = *before,
+        }
+    }
+
+    // Rough heap + inline size, only used for the memory cap
+    fn size_estimate(&self) -> usize {
+        match self {
+            Edit::Clip { id, before, after } => {
+                let clip_size = |clip: &Option<Box<Clip>>| {
+                    clip.as_ref().map_or(0, |c| {
+                        let keyframes: usize = c
+                            .keyframes
+                            .iter()
+                            .map(|(name, track)| {
+                                name.len() + std::mem::size_of_val(track.keyframes())
+                            })
+                            .sum();
+                        std::mem::size_of::<Clip>()
+                            + c.id.len()
+                            + c.path.len()
+                            + c.name.len()
+                            + keyframes
+                    })
+                };
+                std::mem::size_of::<Edit>() + id.len() + clip_size(before) + clip_size(after)
+            }
+            Edit::Track { before, after, .. } => {
+                let name_len = |track: &Option<Track>| track.as_ref().map_or(0, |t| t.name.len());
+                std::mem::size_of::<Edit>() + name_len(before) + name_len(after)
+            }
+            Edit::Marker { id, before, after } => {
+                let name_len = |marker: &Option<Marker>| marker.as_ref().map_or(0, |m| m.name.len());
+                std::mem::size_of::<Edit>() + id.len() + name_len(before) + name_len(after)
+            }
+            Edit::Settings { .. } => std::mem::size_of::<Edit>(),
+        }
+    }
+}
+
+fn set_clip(timeline: &mut Timeline, id: &str, clip: &Option<Box<Clip>>) {
+    match clip {
+        Some(clip) => {
+            timeline.clips.insert(id.to_string(), clip.as_ref().clone());
+        }
+        None => {
+            timeline.clips.remove(id);
+        }
+    }
+}
+
+fn set_track(timeline: &mut Timeline, id: i32, track: &Option<Track>) {
+    match track {
+        Some(track) => {
+            timeline.tracks.insert(id, track.clone());
+        }
+        None => {
+            timeline.tracks.remove(&id);
+        }
+    }
+}
+
+// Markers stay sorted by time
+fn set_marker(timeline: &mut Timeline, id: &str, marker: &Option<Marker>) {
+    timeline.markers.retain(|m| m.id != id);
+    if let Some(marker) = marker {
+        let index = timeline.markers.partition_point(|m| m.time <= marker.time);
+        timeline.markers.insert(index, marker.clone());
+    }
+}
+
+/// One undo step: every edit made by a single user action.
+#[derive(Clone, Debug)]
+pub struct Transaction {
+    pub label: String,
+    pub edits: Vec<Edit>,
+    size: usize,
+}
+
+impl Transaction {
+    fn new(label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            edits: Vec::new(),
+            size: 0,
+        }
+    }
+
+    fn push(&mut self, edit: Edit) {
+        self.size += edit.size_estimate();
+        self.edits.push(edit);
+    }
+}
+
+/// Undo/redo stacks for the timeline.
+/// Edits made between `begin_group` and `end_group` (e.g. a drag) undo as one step.
+#[derive(Debug, Default)]
+pub struct History {
+    undo_stack: VecDeque<Transaction>,
+    redo_stack: Vec<Transaction>,
+    group: Option<Transaction>,
+    group_depth: usize, // Nested groups fold into the outermost one
+    bytes: usize,       // Estimated size of undo_stack
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty() || self.group.as_ref().is_some_and(|g| !g.edits.is_empty())
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    pub fn begin_group(&mut self, label: &str) {
+        if self.group_depth == 0 {
+            self.group = Some(Transaction::new(label));
+        }
+        self.group_depth += 1;
+    }
+
+    pub fn end_group(&mut self) {
+        if self.group_depth == 0 {
+            return;
+        }
+        self.group_depth -= 1;
+        if self.group_depth == 0 {
+            if let Some(group) = self.group.take() {
+                self.push_transaction(group);
+            }
+        }
+    }
+
+    /// Records edits that were already applied to the timeline.
+    pub fn record(&mut self, label: &str, edits: Vec<Edit>) {
+        if edits.is_empty() {
+            return;
+        }
+        match &mut self.group {
+            Some(group) => edits.into_iter().for_each(|edit| group.push(edit)),
+            None => {
+                let mut transaction = Transaction::new(label);
+                edits.into_iter().for_each(|edit| transaction.push(edit));
+                self.push_transaction(transaction);
+            }
+        }
+    }
+
+    /// Reverts the last step. Returns its label, or None if there was nothing to undo.
+    pub fn undo(&mut self, timeline: &mut Timeline) -> Option<String> {
+        self.close_group();
+
+        let transaction = self.undo_stack.pop_back()?;
+        self.bytes -= transaction.size;
+        for edit in transaction.edits.iter().rev() {
+            edit.revert(timeline);
+        }
+
+        let label = transaction.label.clone();
+        self.redo_stack.push(transaction);
+        Some(label)
+    }
+
+    /// Re-applies the last undone step.
+    pub fn redo(&mut self, timeline: &mut Timeline) -> Option<String> {
+        self.close_group();
+
+        let transaction = self.redo_stack.pop()?;
+        for edit in &transaction.edits {
+            edit.apply(timeline);
+        }
+
+        let label = transaction.label.clone();
+        self.bytes += transaction.size;
+        self.undo_stack.push_back(transaction);
+        Some(label)
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    // An undo/redo in the middle of a drag ends the drag
+    fn close_group(&mut self) {
+        if let Some(group) = self.group.take() {
+            self.group_depth = 0;
+            self.push_transaction(group);
+        }
+    }
+
+    fn push_transaction(&mut self, transaction: Transaction) {
+        if transaction.edits.is_empty() {
+            return;
+        }
+
+        // A new edit invalidates anything that was undone
+        self.redo_stack.clear();
+
+        self.bytes += transaction.size;
+        self.undo_stack.push_back(transaction);
+
+        while self.undo_stack.len() > MAX_UNDO_STEPS
+            || (self.bytes > MAX_HISTORY_BYTES && self.undo_stack.len() > 1)
+        {
+            if let Some(dropped) = self.undo_stack.pop_front() {
+                self.bytes -= dropped.size;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::time::{Time, DEFAULT_FRAME_RATE};
+    use crate::engine::timeline::TimelineManager;
+
+    fn clip(id: &str, start: f64) -> Clip {
+        Clip {
+            id: id.into(),
+            path: format!("{}.mp4", id),
+            start: Time::from_seconds(start, DEFAULT_FRAME_RATE),
+            duration: Time::from_seconds(2.0, DEFAULT_FRAME_RATE),
+            track_id: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_undo_redo_add_and_remove() {
+        let mut manager = TimelineManager::new();
+        manager.add_clip(clip("a", 0.0)).unwrap();
+        manager.add_clip(clip("b", 2.0)).unwrap();
+        manager.remove_clip("a").unwrap();
+        assert_eq!(manager.timeline.duration.seconds(), 4.0);
+
+        assert_eq!(manager.undo().as_deref(), Some("Remove Clip"));
+        assert!(manager.timeline.clips.contains_key("a"));
+
+        manager.undo();
+        assert!(!manager.timeline.clips.contains_key("b"));
+        assert_eq!(manager.timeline.duration.seconds(), 2.0);
+
+        manager.redo();
+        manager.redo();
+        assert!(!manager.timeline.clips.contains_key("a"));
+        assert!(manager.redo().is_none());
+    }
+
+    #[test]
+    fn test_group_is_one_step() {
+        let mut manager = TimelineManager::new();
+        manager.add_clip(clip("a", 0.0)).unwrap();
+
+        manager.begin_edit_group("Move Clip");
+        for step in 1..=5 {
+            manager.update_clip(clip("a", step as f64)).unwrap();
+        }
+        manager.end_edit_group();
+        assert_eq!(manager.timeline.clips["a"].start.seconds(), 5.0);
+
+        assert_eq!(manager.undo().as_deref(), Some("Move Clip"));
+        assert_eq!(manager.timeline.clips["a"].start.seconds(), 0.0);
+
+        // New edits clear the redo stack
+        manager.add_clip(clip("b", 4.0)).unwrap();
+        assert!(manager.redo().is_none());
+    }
+
+    #[test]
+    fn test_step_cap_drops_oldest() {
+        let mut history = History::new();
+        let mut timeline = Timeline::new();
+        for i in 0..MAX_UNDO_STEPS + 10 {
+            let added = clip(&i.to_string(), 0.0);
+            let edit = Edit::clip(added.id.clone(), None, Some(added));
+            edit.apply(&mut timeline);
+            history.record("Add Clip", vec![edit]);
+        }
+
+        let mut undone = 0;
+        while history.undo(&mut timeline).is_some() {
+            undone += 1;
+        }
+        assert_eq!(undone, MAX_UNDO_STEPS);
+        assert_eq!(timeline.clips.len(), 10); // Oldest steps are no longer undoable
+    }
+}
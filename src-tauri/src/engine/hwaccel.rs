@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+/// Decoding device requested for decoders opened from now on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HwAccel {
+    /// The platform's devices in order of preference, software if none works
+    #[default]
+    Auto,
+    /// Always decode in software
+    Off,
+    Vaapi,
+    VideoToolbox,
+    D3d11va,
+    Cuda,
+}
+
+/// Where a decoder actually decodes, reported per clip.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DecodePath {
+    Software,
+    Vaapi,
+    VideoToolbox,
+    D3d11va,
+    Cuda,
+}
+
+static PREFERENCE: Mutex<HwAccel> = Mutex::new(HwAccel::Auto);
+
+pub fn preference() -> HwAccel {
+    *PREFERENCE.lock().unwrap()
+}
+
+pub fn set_preference(preference: HwAccel) {
+    *PREFERENCE.lock().unwrap() = preference;
+}
+
+/// Devices to try for `preference`, best first. Empty without the `hwaccel` feature.
+pub fn candidates(preference: HwAccel) -> Vec<DecodePath> {
+    if !cfg!(feature = "hwaccel") {
+        return Vec::new();
+    }
+    match preference {
+        HwAccel::Auto if cfg!(target_os = "macos") => vec![DecodePath::VideoToolbox],
+        HwAccel::Auto if cfg!(target_os = "windows") => vec![DecodePath::Cuda, DecodePath::D3d11va],
+        HwAccel::Auto => vec![DecodePath::Cuda, DecodePath::Vaapi],
+        HwAccel::Off => Vec::new(),
+        HwAccel::Vaapi => vec![DecodePath::Vaapi],
+        HwAccel::VideoToolbox => vec![DecodePath::VideoToolbox],
+        HwAccel::D3d11va => vec![DecodePath::D3d11va],
+        HwAccel::Cuda => vec![DecodePath::Cuda],
+    }
+}
+
+#[cfg(feature = "hwaccel")]
+mod device {
+    use anyhow::Result;
+    use ffmpeg::ffi::*;
+    use std::os::raw::c_void;
+    use std::ptr;
+
+    use super::DecodePath;
+
+    // AV_CODEC_HW_CONFIG_METHOD_HW_DEVICE_CTX (an anonymous enum in codec.h)
+    const METHOD_HW_DEVICE_CTX: i32 = 0x01;
+
+    fn device_type(path: DecodePath) -> Option<AVHWDeviceType> {
+        match path {
+            DecodePath::Software => None,
+            DecodePath::Vaapi => Some(AVHWDeviceType::AV_HWDEVICE_TYPE_VAAPI),
+            DecodePath::VideoToolbox => Some(AVHWDeviceType::AV_HWDEVICE_TYPE_VIDEOTOOLBOX),
+            DecodePath::D3d11va => Some(AVHWDeviceType::AV_HWDEVICE_TYPE_D3D11VA),
+            DecodePath::Cuda => Some(AVHWDeviceType::AV_HWDEVICE_TYPE_CUDA),
+        }
+    }
+
+    // Surface format `codec` decodes into on `device`, if it supports the device at all
+    unsafe fn hw_pixel_format(codec: *const AVCodec, device: AVHWDeviceType) -> Option<AVPixelFormat> {
+        let mut index = 0;
+        loop {
+            let config = avcodec_get_hw_config(codec, index);
+            if config.is_null() {
+                return None;
+            }
+            if (*config).methods & METHOD_HW_DEVICE_CTX != 0 && (*config).device_type == device {
+                return Some((*config).pix_fmt);
+            }
+            index += 1;
+        }
+    }
+
+    // Picks the device's surface format (kept in `opaque`) when the stream offers it,
+    // otherwise lets FFmpeg choose a software one, e.g. for a profile the device can't do
+    unsafe extern "C" fn get_hw_format(context: *mut AVCodecContext, formats: *const AVPixelFormat) -> AVPixelFormat {
+        let wanted = (*context).opaque as isize as i32;
+        let mut format = formats;
+        while *format != AVPixelFormat::AV_PIX_FMT_NONE {
+            if *format as i32 == wanted {
+                return *format;
+            }
+            format = format.add(1);
+        }
+        avcodec_default_get_format(context, formats)
+    }
+
+    /// Sets up decoding on the first of `candidates` that exists and supports the
+    /// codec. Returns the device chosen; None leaves the context on software decoding.
+    pub fn attach(context: &mut ffmpeg::codec::context::Context, candidates: &[DecodePath]) -> Option<DecodePath> {
+        let codec = ffmpeg::decoder::find(context.id())?;
+        for &path in candidates {
+            let Some(device) = device_type(path) else {
+                continue;
+            };
+            unsafe {
+                let Some(pix_fmt) = hw_pixel_format(codec.as_ptr(), device) else {
+                    println!("Decoder: {:?} can't decode {:?}", path, codec.id());
+                    continue;
+                };
+                let mut buffer = ptr::null_mut();
+                let ret = av_hwdevice_ctx_create(&mut buffer, device, ptr::null(), ptr::null_mut(), 0);
+                if ret < 0 {
+                    println!("Decoder: No {:?} device ({})", path, ffmpeg::Error::from(ret));
+                    continue;
+                }
+
+                // The codec context keeps its own reference to the device
+                let raw = context.as_mut_ptr();
+                (*raw).hw_device_ctx = av_buffer_ref(buffer);
+                (*raw).opaque = pix_fmt as i32 as isize as *mut c_void;
+                (*raw).get_format = Some(get_hw_format);
+                av_buffer_unref(&mut buffer);
+                return Some(path);
+            }
+        }
+        None
+    }
+
+    /// True if `frame` is still in device memory and needs `download` before scaling.
+    pub fn is_hardware_frame(frame: &ffmpeg::util::frame::Video) -> bool {
+        unsafe { !(*frame.as_ptr()).hw_frames_ctx.is_null() }
+    }
+
+    /// Copies a frame decoded on the device into system memory (NV12, P010, ...),
+    /// with its pts and color properties.
+    pub fn download(frame: &ffmpeg::util::frame::Video) -> Result<ffmpeg::util::frame::Video> {
+        let mut downloaded = ffmpeg::util::frame::Video::empty();
+        let ret = unsafe { av_hwframe_transfer_data(downloaded.as_mut_ptr(), frame.as_ptr(), 0) };
+        if ret < 0 {
+            return Err(ffmpeg::Error::from(ret).into());
+        }
+        // The transfer only copies pixels; the YUV matrix and range come from the props
+        let ret = unsafe { av_frame_copy_props(downloaded.as_mut_ptr(), frame.as_ptr()) };
+        if ret < 0 {
+            return Err(ffmpeg::Error::from(ret).into());
+        }
+        Ok(downloaded)
+    }
+}
+
+#[cfg(not(feature = "hwaccel"))]
+mod device {
+    use anyhow::Result;
+
+    use super::DecodePath;
+
+    pub fn attach(_context: &mut ffmpeg::codec::context::Context, _candidates: &[DecodePath]) -> Option<DecodePath> {
+        None
+    }
+
+    pub fn is_hardware_frame(_frame: &ffmpeg::util::frame::Video) -> bool {
+        false
+    }
+
+    pub fn download(_frame: &ffmpeg::util::frame::Video) -> Result<ffmpeg::util::frame::Video> {
+        anyhow::bail!("Built without the hwaccel feature")
+    }
+}
+
+pub use device::{attach, download, is_hardware_frame};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidates_follow_preference() {
+        assert!(candidates(HwAccel::Off).is_empty());
+        if cfg!(feature = "hwaccel") {
+            assert_eq!(candidates(HwAccel::Vaapi), [DecodePath::Vaapi]);
+            assert!(!candidates(HwAccel::Auto).is_empty());
+            assert!(!candidates(HwAccel::Auto).contains(&DecodePath::Software));
+        } else {
+            assert!(candidates(HwAccel::Cuda).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_modes_use_lowercase_names() {
+        let mode: HwAccel = serde_json::from_str("\"videotoolbox\"").unwrap();
+        assert_eq!(mode, HwAccel::VideoToolbox);
+        assert_eq!(serde_json::to_string(&DecodePath::D3d11va).unwrap(), "\"d3d11va\"");
+    }
+}
@@ -0,0 +1,235 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::time::{Rational, Time};
+
+// Property names for animated clip transforms.
+// Effect parameters use "effect.<effect>.<param>".
+pub const POSITION_X: &str = "position.x";
+pub const POSITION_Y: &str = "position.y";
+pub const SCALE: &str = "scale";
+pub const ROTATION: &str = "rotation";
+pub const OPACITY: &str = "opacity";
+
+/// How the value moves from this keyframe to the next one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "type")]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    Hold, // Keep this value until the next keyframe
+    /// CSS-style cubic-bezier(x1, y1, x2, y2) ease
+    Bezier { x1: f32, y1: f32, x2: f32, y2: f32 },
+}
+
+impl Interpolation {
+    /// Maps linear progress (0..1) through the curve.
+    pub fn ease(&self, progress: f32) -> f32 {
+        match *self {
+            Interpolation::Linear => progress,
+            Interpolation::Hold => 0.0,
+            Interpolation::Bezier { x1, y1, x2, y2 } => cubic_bezier(x1, y1, x2, y2, progress),
+        }
+    }
+}
+
+// Solves x(t) = progress by bisection, then returns y(t)
+fn cubic_bezier(x1: f32, y1: f32, x2: f32, y2: f32, progress: f32) -> f32 {
+    let bezier = |a: f32, b: f32, t: f32| {
+        let mt = 1.0 - t;
+        3.0 * mt * mt * t * a + 3.0 * mt * t * t * b + t * t * t
+    };
+
+    let x = progress.clamp(0.0, 1.0);
+    let (mut lo, mut hi) = (0.0f32, 1.0f32);
+    let mut t = x;
+    for _ in 0..32 {
+        let current = bezier(x1.clamp(0.0, 1.0), x2.clamp(0.0, 1.0), t);
+        if (current - x).abs() < 1e-5 {
+            break;
+        }
+        if current < x {
+            lo = t;
+        } else {
+            hi = t;
+        }
+        t = (lo + hi) * 0.5;
+    }
+    bezier(y1, y2, t)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Keyframe {
+    pub time: Time, // Media time (into the source), see Clip::get_media_time
+    pub value: f32,
+    #[serde(default)]
+    pub interpolation: Interpolation, // Curve towards the next keyframe
+}
+
+/// Keyframes for one property, kept sorted by time.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Vec<Keyframe>", into = "Vec<Keyframe>")]
+pub struct KeyframeTrack {
+    keyframes: Vec<Keyframe>,
+}
+
+// The frontend may send keyframes in any order
+impl From<Vec<Keyframe>> for KeyframeTrack {
+    fn from(mut keyframes: Vec<Keyframe>) -> Self {
+        keyframes.sort_by_key(|k| k.time);
+        Self { keyframes }
+    }
+}
+
+impl From<KeyframeTrack> for Vec<Keyframe> {
+    fn from(track: KeyframeTrack) -> Self {
+        track.keyframes
+    }
+}
+
+impl KeyframeTrack {
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    /// Adds a keyframe, replacing one at the same time.
+    pub fn insert(&mut self, keyframe: Keyframe) {
+        match self.keyframes.binary_search_by_key(&keyframe.time, |k| k.time) {
+            Ok(index) => self.keyframes[index] = keyframe,
+            Err(index) => self.keyframes.insert(index, keyframe),
+        }
+    }
+
+    /// Removes the keyframe at exactly `time`. Returns false if there was none.
+    pub fn remove(&mut self, time: Time) -> bool {
+        match self.keyframes.binary_search_by_key(&time, |k| k.time) {
+            Ok(index) => {
+                self.keyframes.remove(index);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Rounds keyframe times to whole frames at `rate`. Of keyframes landing on
+    /// the same frame, the later one wins.
+    pub fn conform(&mut self, rate: Rational) {
+        for mut keyframe in std::mem::take(&mut self.keyframes) {
+            keyframe.time = keyframe.time.rescale(rate);
+            self.insert(keyframe);
+        }
+    }
+
+    /// Value at `time`. Holds the first/last value outside the keyed range.
+    pub fn evaluate(&self, time: Time) -> Option<f32> {
+        let first = self.keyframes.first()?;
+        let last = self.keyframes.last()?;
+
+        if time <= first.time {
+            return Some(first.value);
+        }
+        if time >= last.time {
+            return Some(last.value);
+        }
+
+        // Segment [a, b) containing `time`
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        let (a, b) = (&self.keyframes[next - 1], &self.keyframes[next]);
+
+        // In seconds, so keyframes at different rates don't round
+        let progress = ((time.seconds() - a.time.seconds()) / (b.time.seconds() - a.time.seconds())) as f32;
+        let eased = a.interpolation.ease(progress);
+        Some(a.value + (b.value - a.value) * eased)
+    }
+}
+
+/// Animated properties of a clip, keyed by property name.
+/// Serialized as `{ "opacity": [{ "time": 0, "value": 1, ... }], ... }`.
+pub type ClipKeyframes = BTreeMap<String, KeyframeTrack>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::time::MICROSECONDS;
+
+    const FPS: Rational = Rational { num: 30, den: 1 };
+
+    fn t(seconds: f64) -> Time {
+        Time::from_seconds(seconds, FPS)
+    }
+
+    fn key(time: f64, value: f32, interpolation: Interpolation) -> Keyframe {
+        Keyframe {
+            time: t(time),
+            value,
+            interpolation,
+        }
+    }
+
+    #[test]
+    fn test_linear_and_hold() {
+        let mut track = KeyframeTrack::default();
+        assert_eq!(track.evaluate(t(1.0)), None);
+
+        track.insert(key(2.0, 10.0, Interpolation::Hold));
+        track.insert(key(0.0, 0.0, Interpolation::Linear));
+        track.insert(key(4.0, 0.0, Interpolation::Linear));
+
+        assert_eq!(track.evaluate(t(-1.0)), Some(0.0)); // Before first
+        assert_eq!(track.evaluate(t(1.0)), Some(5.0)); // Linear midpoint
+        assert_eq!(track.evaluate(t(3.9)), Some(10.0)); // Hold
+        assert_eq!(track.evaluate(t(9.0)), Some(0.0)); // After last
+
+        // Exact match only, at any rate
+        assert!(!track.remove(Time::from_frames(2_000_001, MICROSECONDS)));
+        assert!(track.remove(Time::from_frames(2_000_000, MICROSECONDS)));
+        assert_eq!(track.evaluate(t(2.0)), Some(0.0));
+    }
+
+    #[test]
+    fn test_conform_to_frames() {
+        let mut track = KeyframeTrack::default();
+        track.insert(Keyframe {
+            time: Time::from_seconds(0.99, MICROSECONDS),
+            ..key(0.0, 1.0, Interpolation::Linear)
+        });
+        track.insert(Keyframe {
+            time: Time::from_seconds(1.01, MICROSECONDS),
+            ..key(0.0, 2.0, Interpolation::Linear)
+        });
+
+        // Both round to frame 30; the later keyframe wins
+        track.conform(FPS);
+        assert_eq!(track.keyframes().len(), 1);
+        assert_eq!(track.keyframes()[0].time, t(1.0));
+        assert_eq!(track.keyframes()[0].value, 2.0);
+    }
+
+    #[test]
+    fn test_bezier_ease() {
+        let ease_in_out = Interpolation::Bezier {
+            x1: 0.42,
+            y1: 0.0,
+            x2: 0.58,
+            y2: 1.0,
+        };
+        assert!(ease_in_out.ease(0.0).abs() < 1e-3);
+        assert!((ease_in_out.ease(0.5) - 0.5).abs() < 1e-3);
+        assert!((ease_in_out.ease(1.0) - 1.0).abs() < 1e-3);
+        assert!(ease_in_out.ease(0.25) < 0.25); // Slow start
+    }
+
+    #[test]
+    fn test_serialized_shape() {
+        let json = r#"[{"time":1.0,"value":3.0},{"time":0.5,"value":2.0,"interpolation":{"type":"bezier","x1":0.25,"y1":0.1,"x2":0.25,"y2":1.0}}]"#;
+        let track: KeyframeTrack = serde_json::from_str(json).unwrap();
+        assert_eq!(track.keyframes().len(), 2);
+        assert_eq!(track.keyframes()[0].time, t(0.5)); // Sorted on load
+        assert_eq!(track.keyframes()[1].interpolation, Interpolation::Linear);
+    }
+}
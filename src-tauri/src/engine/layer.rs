@@ -0,0 +1,418 @@
+use anyhow::Result;
+use std::collections::HashSet;
+use std::sync::Arc;
+use wgpu::util::DeviceExt;
+
+use super::decode_worker::DecodeWorker;
+use super::frame_cache::{CachedFrame, FrameCache};
+use super::hwaccel::DecodePath;
+use super::model::{ColorUniform, TransformUniform};
+use super::time::{Rational, Time};
+use super::video_frame::{plane_sizes, PlaneLayout, VideoFrame};
+
+// Layers further behind than this seek instead of decoding forward
+const CATCH_UP_THRESHOLD: f64 = 1.0; // Seconds
+// Frames taken from the decode queue per prefetch step, so idle work stays short
+const PREFETCH_BATCH: usize = 4;
+
+/// Plane textures, color uniform and bind group sized to the source, created when
+/// its first frame arrives (and again if the frame layout or size changes).
+struct LayerTexture {
+    layout: PlaneLayout,
+    planes: Vec<wgpu::Texture>, // Always three (see video.wgsl); unused ones are 1x1
+    color_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    frame_bytes: usize,
+}
+
+/// One video source on the GPU: a decode thread, plane textures and bind group.
+/// The compositor keeps one per active clip and draws them bottom to top.
+/// Nothing here waits on the decoder; frames are uploaded once they are ready.
+pub struct ClipLayer {
+    pub path: String,
+    worker: DecodeWorker,
+    device: wgpu::Device,
+    layout: wgpu::BindGroupLayout,
+    texture: Option<LayerTexture>,
+    pub transform_buffer: wgpu::Buffer,
+    pub transform_bind_group: wgpu::BindGroup,
+    pub pts: Time, // Media time of the frame currently in the texture
+    seek_target: Time, // Where the decode thread was last sent
+    decoder_pts: Option<Time>, // Last frame received since then (None = seek in flight); cache hits leave it behind `pts`
+    next: Option<CachedFrame>, // Received but not due yet
+    prefetch_center: Option<i64>, // Frame index the prefetch window is around
+    prefetch_skip: HashSet<i64>,  // Frames in the window that could not be decoded
+}
+
+impl ClipLayer {
+    /// Starts decoding `path` in the background; the layer draws once its first frame is in.
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        transform_layout: &wgpu::BindGroupLayout,
+        path: &str,
+    ) -> Result<Self> {
+        // 1. Start the decode thread (it opens the file and decodes from the start)
+        let worker = DecodeWorker::spawn(path)?;
+
+        // 2. Create Transform Uniform
+        let transform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Transform Buffer"),
+            contents: bytemuck::bytes_of(&TransformUniform::default()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let transform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: transform_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: transform_buffer.as_entire_binding(),
+            }],
+            label: Some("Transform Bind Group"),
+        });
+
+        Ok(Self {
+            path: path.to_string(),
+            worker,
+            device: device.clone(),
+            layout: layout.clone(),
+            texture: None,
+            transform_buffer,
+            transform_bind_group,
+            pts: Time::default(),
+            seek_target: Time::default(),
+            decoder_pts: None,
+            next: None,
+            prefetch_center: None,
+            prefetch_skip: HashSet::new(),
+        })
+    }
+
+    /// Texture bind group, once a frame has been uploaded.
+    pub fn bind_group(&self) -> Option<&wgpu::BindGroup> {
+        self.texture.as_ref().map(|t| &t.bind_group)
+    }
+
+    /// Source frame size, once a frame has been uploaded.
+    pub fn source_size(&self) -> Option<(u32, u32)> {
+        self.texture
+            .as_ref()
+            .map(|t| (t.planes[0].width(), t.planes[0].height()))
+    }
+
+    /// Hardware device or software, once the file is open.
+    pub fn decode_path(&mut self) -> Option<DecodePath> {
+        self.worker.info().map(|info| info.decode_path)
+    }
+
+    fn stream_size(&mut self) -> Option<(u32, u32)> {
+        self.worker.info().map(|info| (info.width, info.height))
+    }
+
+    /// Bytes in one decoded frame (RGBA estimate until one arrives, 0 until the file is open).
+    pub fn frame_bytes(&mut self) -> usize {
+        if let Some(layer_texture) = &self.texture {
+            return layer_texture.frame_bytes;
+        }
+        self.stream_size().map_or(0, |(w, h)| (w * h * 4) as usize)
+    }
+
+    fn create_texture(&self, frame: &VideoFrame) -> LayerTexture {
+        let sizes = plane_sizes(frame.layout, frame.width, frame.height);
+        let planes: Vec<wgpu::Texture> = (0..3)
+            .map(|index| {
+                let (width, height, bytes_per_pixel) = sizes.get(index).copied().unwrap_or((1, 1, 1));
+                self.device.create_texture(&wgpu::TextureDescriptor {
+                    label: Some("Video Plane Texture"),
+                    size: wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: match bytes_per_pixel {
+                        4 => wgpu::TextureFormat::Rgba8Unorm, // RGBA
+                        2 => wgpu::TextureFormat::Rg8Unorm,   // Interleaved UV
+                        _ => wgpu::TextureFormat::R8Unorm,    // Y, U or V
+                    },
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                    view_formats: &[],
+                })
+            })
+            .collect();
+        let views: Vec<wgpu::TextureView> = planes
+            .iter()
+            .map(|plane| plane.create_view(&wgpu::TextureViewDescriptor::default()))
+            .collect();
+
+        let sampler = self.device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::MipmapFilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let color_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Color Conversion Buffer"),
+            contents: bytemuck::bytes_of(&ColorUniform::new(frame)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&views[0]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&views[1]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&views[2]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: color_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("Video Bind Group"),
+        });
+
+        LayerTexture {
+            layout: frame.layout,
+            planes,
+            color_buffer,
+            bind_group,
+            frame_bytes: frame.byte_size(),
+        }
+    }
+
+    /// Uploads each plane as is, row padding skipped by the copy; YUV is converted
+    /// to RGB when drawing. The frame's buffers can go back to the pool afterwards.
+    pub fn upload(&mut self, queue: &wgpu::Queue, frame: &VideoFrame) {
+        let stale = self.texture.as_ref().is_none_or(|t| {
+            t.layout != frame.layout || t.planes[0].width() != frame.width || t.planes[0].height() != frame.height
+        });
+        if stale {
+            self.texture = Some(self.create_texture(frame));
+        }
+        let Some(layer_texture) = &self.texture else {
+            return;
+        };
+
+        let sizes = plane_sizes(frame.layout, frame.width, frame.height);
+        for ((texture, plane), (width, height, _)) in
+            layer_texture.planes.iter().zip(&frame.planes).zip(sizes)
+        {
+            queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &plane.data,
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(plane.stride),
+                    rows_per_image: Some(height),
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+        // Matrix and range can change between frames of a stream
+        queue.write_buffer(&layer_texture.color_buffer, 0, bytemuck::bytes_of(&ColorUniform::new(frame)));
+    }
+
+    pub fn set_transform(&self, queue: &wgpu::Queue, uniform: &TransformUniform) {
+        queue.write_buffer(&self.transform_buffer, 0, bytemuck::bytes_of(uniform));
+    }
+
+    fn frame_rate(&mut self) -> Option<Rational> {
+        self.worker.info().map(|info| info.frame_rate)
+    }
+
+    /// True if the texture holds the frame due at `media_time` (or one a hair
+    /// ahead of it, so a jittery clock doesn't step back and forth).
+    pub fn shows(&mut self, media_time: Time) -> bool {
+        let Some(rate) = self.frame_rate() else {
+            return false;
+        };
+        let frame_duration = Time::from_frames(1, rate);
+        self.texture.is_some() && media_time >= self.pts - frame_duration && media_time < self.pts + frame_duration
+    }
+
+    /// Why the file can't be decoded, once its decode thread failed to open it.
+    pub fn failure(&mut self) -> Option<&str> {
+        self.worker.failure()
+    }
+
+    /// True once waiting won't change what the layer draws at `media_time`: it
+    /// shows that frame, the stream ended before it, or the file failed to open.
+    pub fn is_settled(&mut self, media_time: Time) -> bool {
+        self.shows(media_time)
+            || self.failure().is_some()
+            || (self.worker.is_end_of_stream() && self.next.is_none())
+    }
+
+    /// True if the layer shows `media_time` or the decode thread is heading there.
+    pub fn is_cued(&mut self, media_time: Time) -> bool {
+        self.shows(media_time) || (self.seek_target == media_time && self.decoder_pts.is_none())
+    }
+
+    fn show(&mut self, queue: &wgpu::Queue, frame: &CachedFrame) {
+        self.upload(queue, &frame.frame);
+        self.pts = frame.pts;
+    }
+
+    /// Shows the cached frame due at `media_time`, if there is one.
+    fn show_cached(&mut self, queue: &wgpu::Queue, cache: &mut FrameCache, media_time: Time) -> bool {
+        let Some(rate) = self.frame_rate() else {
+            return false;
+        };
+        match cache.get(&self.path, media_time.floor_to(rate).value) {
+            Some(frame) => {
+                self.show(queue, &frame);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Takes the next ready frame from the decode thread and keeps it in the cache.
+    fn receive(&mut self, cache: &mut FrameCache) -> Option<CachedFrame> {
+        if let Some(frame) = self.next.take() {
+            return Some(frame); // Already cached
+        }
+        let rate = self.frame_rate()?;
+        let decoded = self.worker.try_next()?;
+        self.decoder_pts = Some(decoded.pts);
+        let frame = CachedFrame {
+            frame: Arc::new(decoded.frame),
+            pts: decoded.pts,
+        };
+        // Nearest frame, so streams starting slightly off the grid still match `floor_to`
+        cache.insert(&self.path, frame.pts.rescale(rate).value, frame.clone());
+        Some(frame)
+    }
+
+    fn request_seek(&mut self, media_time: Time) {
+        self.worker.seek(media_time);
+        self.seek_target = media_time;
+        self.decoder_pts = None;
+        self.next = None;
+    }
+
+    /// Jumps to `media_time`: shows it right away if cached, otherwise once decoded.
+    pub fn seek(&mut self, queue: &wgpu::Queue, cache: &mut FrameCache, media_time: Time) {
+        if self.show_cached(queue, cache, media_time) {
+            return;
+        }
+        if self.seek_target != media_time || self.decoder_pts.is_some() {
+            self.request_seek(media_time);
+        }
+        self.advance_to(queue, cache, media_time); // In case it's already decoded
+    }
+
+    /// Shows the newest ready frame due at `media_time`. Keeps the current frame
+    /// (and lets the decode thread catch up) if it isn't decoded yet.
+    pub fn advance_to(&mut self, queue: &wgpu::Queue, cache: &mut FrameCache, media_time: Time) {
+        let Some(rate) = self.frame_rate() else {
+            return; // Still opening
+        };
+        if self.shows(media_time) || self.show_cached(queue, cache, media_time) {
+            return;
+        }
+
+        // The decode thread continues from the last frame received (or the seek in flight),
+        // which cache hits may have left behind
+        let frame_duration = Time::from_frames(1, rate);
+        let position = self
+            .next
+            .as_ref()
+            .map(|frame| frame.pts)
+            .or(self.decoder_pts)
+            .unwrap_or(self.seek_target);
+        if media_time < position - frame_duration
+            || (media_time - position).seconds() > CATCH_UP_THRESHOLD
+        {
+            self.request_seek(media_time);
+        }
+
+        // Only the newest due frame is uploaded if we fell behind
+        let mut latest = None;
+        while let Some(frame) = self.receive(cache) {
+            if frame.pts > media_time {
+                self.next = Some(frame);
+                break;
+            }
+            latest = Some(frame);
+        }
+
+        if let Some(frame) = latest {
+            self.show(queue, &frame);
+        }
+    }
+
+    /// Moves a few decoded frames within `radius` frames of `media_time` into the
+    /// cache, sending the decode thread to the gaps: ahead of the playhead first,
+    /// then the run behind it. Returns false once the window is complete.
+    pub fn prefetch(&mut self, cache: &mut FrameCache, media_time: Time, radius: i64) -> bool {
+        let Some(rate) = self.frame_rate() else {
+            return false;
+        };
+        let center = media_time.floor_to(rate).value;
+        if self.prefetch_center != Some(center) {
+            self.prefetch_center = Some(center);
+            self.prefetch_skip.clear();
+        }
+
+        // Whatever is ready goes into the cache, so the decode thread keeps going
+        for _ in 0..PREFETCH_BATCH {
+            let Some(frame) = self.receive(cache) else {
+                break;
+            };
+            if frame.pts.rescale(rate).value >= center + radius {
+                self.next = Some(frame); // Window end: let the queue fill up behind it
+                break;
+            }
+        }
+
+        let missing = |index: &i64| !cache.contains(&self.path, *index) && !self.prefetch_skip.contains(index);
+        let Some(target) = (center..=center + radius)
+            .find(missing)
+            .or_else(|| ((center - radius).max(0)..center).find(missing))
+        else {
+            return false;
+        };
+
+        let requested = self.seek_target.floor_to(rate).value;
+        match self.decoder_pts.map(|pts| pts.rescale(rate).value) {
+            // Seek in flight: wait unless it lands past the target
+            None if requested <= target => {}
+            // Frames up to the target are on their way
+            Some(position) if position < target && !self.worker.is_end_of_stream() => {}
+            // Already sent there and it never came (past the end, irregular PTS)
+            _ if requested == target => {
+                self.prefetch_skip.insert(target);
+            }
+            _ => self.request_seek(Time::from_frames(target, rate)),
+        }
+        true
+    }
+}
@@ -0,0 +1,342 @@
+use std::collections::{HashMap, HashSet};
+
+use super::history::Edit;
+use super::time::Time;
+use super::timeline::{Clip, TimelineManager};
+
+// Magnetic tracks hold a gapless storyline starting at 0s: clips are laid out
+// back to back in start order after every edit (locked tracks are left alone).
+// Clips on other, non-magnetic tracks can be connected to a clip
+// (`Clip::connected_to`) and then move with it, unless that would land them on
+// another clip: they then stay put and are disconnected.
+
+impl TimelineManager {
+    pub fn is_magnetic(&self, track_id: i32) -> bool {
+        self.timeline
+            .tracks
+            .get(&track_id)
+            .is_some_and(|track| track.is_magnetic)
+    }
+
+    /// Snaps `time` to the closest edit point (clip boundary) on a magnetic track.
+    pub fn nearest_edit_point(&self, track_id: i32, time: Time) -> Time {
+        let zero = Time::zero(self.frame_rate());
+        self.timeline
+            .clips
+            .values()
+            .filter(|c| c.track_id == track_id)
+            .flat_map(|c| [c.start, c.end()])
+            .chain([zero])
+            .min_by_key(|point| (*point - time).abs())
+            .unwrap_or(zero)
+    }
+
+    /// Connects a clip to an anchor clip on another track, or disconnects it (`None`).
+    pub fn connect_clip(&mut self, clip_id: &str, anchor_id: Option<&str>) -> Result<(), String> {
+        let before = self
+            .timeline
+            .clips
+            .get(clip_id)
+            .cloned()
+            .ok_or(format!("Clip not found: {}", clip_id))?;
+        self.check_unlocked(before.track_id)?;
+
+        if let Some(anchor_id) = anchor_id {
+            if self.is_magnetic(before.track_id) {
+                return Err("Clips on a magnetic track can't be connected".to_string());
+            }
+            let anchor = self
+                .timeline
+                .clips
+                .get(anchor_id)
+                .ok_or(format!("Clip not found: {}", anchor_id))?;
+            if anchor.track_id == before.track_id {
+                return Err("A clip can only connect to a clip on another track".to_string());
+            }
+            if anchor.connected_to.is_some() {
+                return Err(format!("Clip {} is itself connected", anchor.name));
+            }
+            // Connections are one level deep: an anchor can't follow another clip
+            if self
+                .timeline
+                .clips
+                .values()
+                .any(|c| c.connected_to.as_deref() == Some(clip_id))
+            {
+                return Err(format!("Clip {} has clips connected to it", before.name));
+            }
+        }
+
+        let mut after = before.clone();
+        after.connected_to = anchor_id.map(str::to_string);
+        let label = if anchor_id.is_some() {
+            "Connect Clip"
+        } else {
+            "Disconnect Clip"
+        };
+        self.commit(
+            label,
+            vec![Edit::clip(clip_id.to_string(), Some(before), Some(after))],
+        );
+        Ok(())
+    }
+
+    /// Follow-up edits after `edits` were applied: closes gaps on magnetic tracks and
+    /// moves connected clips with their anchors. `starts` holds clip starts from before.
+    pub(super) fn magnetic_edits(
+        &self,
+        starts: &HashMap<String, Time>,
+        edits: &[Edit],
+    ) -> Vec<Edit> {
+        let mut follow_up = Vec::new();
+        let mut current: HashMap<String, Clip> = HashMap::new(); // Clips changed here
+
+        // 1. Repack magnetic tracks
+        for track in self
+            .timeline
+            .tracks
+            .values()
+            .filter(|t| t.is_magnetic && self.is_editable_track(t.id))
+        {
+            let mut clips: Vec<&Clip> = self
+                .timeline
+                .clips
+                .values()
+                .filter(|c| c.track_id == track.id)
+                .collect();
+            clips.sort_by(|a, b| a.start.cmp(&b.start).then_with(|| a.id.cmp(&b.id)));
+
+            let mut cursor = Time::zero(self.frame_rate());
+            for clip in clips {
+                if clip.start != cursor {
+                    let mut packed = clip.clone();
+                    packed.start = cursor;
+                    current.insert(clip.id.clone(), packed.clone());
+                    follow_up.push(Edit::clip(
+                        clip.id.clone(),
+                        Some(clip.clone()),
+                        Some(packed),
+                    ));
+                }
+                cursor += clip.duration;
+            }
+        }
+
+        // 2. Connected clips follow their anchor, unless they were edited themselves
+        let edited: HashSet<&str> = edits
+            .iter()
+            .filter_map(|edit| match edit {
+                Edit::Clip { id, .. } => Some(id.as_str()),
+                _ => None,
+            })
+            .collect();
+
+        let mut moves: Vec<(&Clip, Clip)> = Vec::new();
+        for clip in self.timeline.clips.values() {
+            let Some(anchor_id) = &clip.connected_to else {
+                continue;
+            };
+            if edited.contains(clip.id.as_str())
+                || !self.is_editable_track(clip.track_id)
+                || self.is_magnetic(clip.track_id) // Repacked above
+            {
+                continue;
+            }
+
+            let anchor = current
+                .get(anchor_id)
+                .or_else(|| self.timeline.clips.get(anchor_id));
+            let mut moved = clip.clone();
+            match (anchor, starts.get(anchor_id)) {
+                (Some(anchor), Some(previous_start)) => {
+                    let delta = anchor.start - *previous_start;
+                    if delta.is_zero() {
+                        continue;
+                    }
+                    moved.start = (moved.start + delta).max(Time::zero(self.frame_rate()));
+                }
+                // Anchor was removed: the clip stays where it is
+                (None, _) => moved.connected_to = None,
+                _ => continue,
+            }
+            moves.push((clip, moved));
+        }
+
+        // 3. A move onto another clip is dropped: the clip stays and is disconnected.
+        // Repeat, since a clip staying behind can be in the way of another one
+        loop {
+            let blocked: Vec<usize> = (0..moves.len())
+                .filter(|&i| {
+                    let moved = &moves[i].1;
+                    moved.connected_to.is_some() && self.lands_on_clip(moved, &moves)
+                })
+                .collect();
+            if blocked.is_empty() {
+                break;
+            }
+            for i in blocked {
+                let (clip, moved) = &mut moves[i];
+                *moved = Clip {
+                    connected_to: None,
+                    ..(*clip).clone()
+                };
+            }
+        }
+
+        follow_up.extend(
+            moves
+                .into_iter()
+                .map(|(clip, moved)| Edit::clip(clip.id.clone(), Some(clip.clone()), Some(moved))),
+        );
+        follow_up
+    }
+
+    // Whether `moved` overlaps another clip on its track once `moves` are applied
+    fn lands_on_clip(&self, moved: &Clip, moves: &[(&Clip, Clip)]) -> bool {
+        self.timeline
+            .clips
+            .values()
+            .filter(|c| c.track_id == moved.track_id && c.id != moved.id)
+            .map(|c| {
+                moves
+                    .iter()
+                    .find(|(before, _)| before.id == c.id)
+                    .map_or(c, |(_, after)| after)
+            })
+            .any(|c| c.overlaps(moved.start, moved.end()))
+    }
+
+    fn is_editable_track(&self, track_id: i32) -> bool {
+        self.check_unlocked(track_id).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::placement::PlacementPolicy;
+    use crate::engine::time::DEFAULT_FRAME_RATE;
+    use crate::engine::timeline::TrackKind;
+
+    fn t(seconds: f64) -> Time {
+        Time::from_seconds(seconds, DEFAULT_FRAME_RATE)
+    }
+
+    fn clip(id: &str, track_id: i32, start: f64, duration: f64) -> Clip {
+        Clip {
+            id: id.into(),
+            start: t(start),
+            duration: t(duration),
+            track_id,
+            ..Default::default()
+        }
+    }
+
+    fn start(manager: &TimelineManager, id: &str) -> f64 {
+        manager.timeline.clips[id].start.seconds()
+    }
+
+    #[test]
+    fn test_storyline_stays_contiguous() {
+        let mut manager = TimelineManager::new();
+        manager.add_clip(clip("a", 1, 1.0, 2.0)).unwrap();
+        manager.add_clip(clip("b", 1, 5.0, 2.0)).unwrap();
+
+        // Enabling closes existing gaps
+        manager.set_track_magnetic(1, true).unwrap();
+        assert_eq!((start(&manager, "a"), start(&manager, "b")), (0.0, 2.0));
+
+        // Dropping onto "b" inserts at the nearest edit point and pushes "b"
+        manager
+            .place_clip(clip("c", 1, 2.4, 1.0), PlacementPolicy::Reject)
+            .unwrap();
+        assert_eq!((start(&manager, "c"), start(&manager, "b")), (2.0, 3.0));
+
+        // Removing closes the gap
+        manager.remove_clip("a").unwrap();
+        assert_eq!((start(&manager, "c"), start(&manager, "b")), (0.0, 1.0));
+        assert_eq!(manager.timeline.duration.seconds(), 3.0);
+
+        // Trimming pushes the rest instead of failing on overlap
+        manager.trim_out("c", t(2.0)).unwrap();
+        assert_eq!(start(&manager, "b"), 2.0);
+    }
+
+    #[test]
+    fn test_connected_clips_follow_anchor() {
+        let mut manager = TimelineManager::new();
+        manager.set_track_magnetic(1, true).unwrap();
+        manager.add_clip(clip("a", 1, 0.0, 2.0)).unwrap();
+        manager.add_clip(clip("b", 1, 2.0, 2.0)).unwrap();
+        manager.add_clip(clip("title", 2, 2.5, 1.0)).unwrap();
+        manager.connect_clip("title", Some("b")).unwrap();
+
+        manager.remove_clip("a").unwrap();
+        assert_eq!(start(&manager, "b"), 0.0);
+        assert_eq!(start(&manager, "title"), 0.5);
+
+        manager.undo();
+        assert_eq!(start(&manager, "title"), 2.5);
+
+        // Removing the anchor leaves the clip in place, disconnected
+        manager.remove_clip("b").unwrap();
+        assert_eq!(start(&manager, "title"), 2.5);
+        assert!(manager.timeline.clips["title"].connected_to.is_none());
+    }
+
+    #[test]
+    fn test_connected_clip_never_lands_on_another() {
+        let mut manager = TimelineManager::new();
+        manager.set_track_magnetic(1, true).unwrap();
+        manager.add_clip(clip("a", 1, 0.0, 2.0)).unwrap();
+        manager.add_clip(clip("b", 1, 2.0, 2.0)).unwrap();
+        manager.add_clip(clip("logo", 2, 0.0, 1.0)).unwrap();
+        manager.add_clip(clip("title", 2, 2.5, 1.0)).unwrap();
+        manager.add_clip(clip("credit", 2, 3.5, 1.5)).unwrap();
+        manager.connect_clip("title", Some("b")).unwrap();
+        manager.connect_clip("credit", Some("b")).unwrap();
+
+        // "title" would land on "logo": it stays, and so does "credit" behind it
+        manager.remove_clip("a").unwrap();
+        assert_eq!(start(&manager, "b"), 0.0);
+        assert_eq!((start(&manager, "title"), start(&manager, "credit")), (2.5, 3.5));
+        assert!(manager.timeline.clips["title"].connected_to.is_none());
+        assert!(manager.timeline.clips["credit"].connected_to.is_none());
+
+        // Storyline clips can't be connected
+        assert!(manager.connect_clip("b", Some("logo")).is_err());
+    }
+
+    #[test]
+    fn test_connections_are_one_level_deep() {
+        let mut manager = TimelineManager::new();
+        let titles = manager.add_track("Titles", TrackKind::Video);
+        manager.add_clip(clip("a", 1, 0.0, 2.0)).unwrap();
+        manager.add_clip(clip("title", 2, 0.0, 1.0)).unwrap();
+        manager.add_clip(clip("subtitle", titles.id, 0.0, 1.0)).unwrap();
+        manager.connect_clip("title", Some("a")).unwrap();
+
+        // "title" is connected, so it can't be an anchor
+        assert!(manager.connect_clip("subtitle", Some("title")).is_err());
+        // "a" is an anchor, so it can't be connected
+        assert!(manager.connect_clip("a", Some("subtitle")).is_err());
+        assert!(manager.timeline.clips["a"].connected_to.is_none());
+
+        // Once free of dependents it can
+        manager.connect_clip("title", None).unwrap();
+        manager.connect_clip("a", Some("subtitle")).unwrap();
+    }
+
+    #[test]
+    fn test_locked_storyline_is_not_repacked() {
+        let mut timeline = TimelineManager::new().timeline;
+        let track = timeline.tracks.get_mut(&1).unwrap();
+        track.is_magnetic = true;
+        track.is_locked = true;
+        timeline.clips.insert("a".into(), clip("a", 1, 1.0, 2.0)); // A gap, as loaded
+        let mut manager = TimelineManager::from_timeline(timeline);
+
+        manager.add_clip(clip("title", 2, 0.0, 1.0)).unwrap();
+        assert_eq!(start(&manager, "a"), 1.0);
+    }
+}
@@ -1,11 +1,33 @@
+use std::collections::HashMap;
 use wgpu::{Adapter, Device, Instance, Queue, Surface};
 
+pub mod audio;
+pub mod audio_worker;
+pub mod decoder_pool;
 pub mod decoding;
+pub mod editing;
+pub mod export;
 pub mod export_utils;
+pub mod frame_cache;
+pub mod frame_pool;
+pub mod framing;
+#[cfg(test)]
+mod golden;
+pub mod history;
+pub mod hwaccel;
+pub mod keyframes;
+pub mod layer;
+pub mod magnetic;
 pub mod model;
+pub mod placement;
+pub mod project;
 pub mod proxy_manager;
 pub mod renderer;
+pub mod settings;
+pub mod snapping;
+pub mod time;
 pub mod timeline;
+pub mod video_frame;
 
 pub struct KinetixEngine {
     pub instance: Instance,
@@ -14,10 +36,17 @@ pub struct KinetixEngine {
     pub device: Option<Device>,
     pub queue: Option<Queue>,
     pub render_pipeline: Option<wgpu::RenderPipeline>,
+    pub background_pipeline: Option<wgpu::RenderPipeline>, // Fills the canvas rect with the project background
+    pub target_format: Option<wgpu::TextureFormat>, // Surface format, or the offscreen one when headless
     pub texture_bind_group_layout: Option<wgpu::BindGroupLayout>, // [NEW] Layout for creating texture bind groups
-    pub texture_bind_group: Option<wgpu::BindGroup>, // [NEW] Holds the active video frame texture
-    pub video_texture: Option<wgpu::Texture>,        // [NEW] The actual texture resource
-    pub decoder: Option<crate::engine::decoding::VideoDecoder>, // [NEW] Video Decoder
+    pub transform_bind_group_layout: Option<wgpu::BindGroupLayout>, // Per-layer TransformUniform
+    pub source_layer: Option<layer::ClipLayer>, // File opened via load_video (see `shows_source`)
+    pub source_preview: bool, // load_video's file stays on screen until a seek or play
+    pub layers: HashMap<String, layer::ClipLayer>, // One per active timeline clip, keyed by clip ID
+    pub layer_order: Vec<String>, // Clip IDs bottom to top (Z-Index order)
+    pub decoder_pool: decoder_pool::DecoderPool, // Warm layers for upcoming and just-finished clips
+    pub audio: audio::AudioPlayback, // Mixed timeline audio + master clock
+    pub frame_cache: frame_cache::FrameCache, // Decoded frames shared by all layers (LRU)
 
     // State
     pub current_file: Option<String>,
@@ -33,31 +62,21 @@ pub struct KinetixEngine {
 
 use serde::Serialize;
 
-#[derive(Debug, Clone, Copy, Serialize)]
+#[derive(Debug, Clone, Copy, Default, Serialize)]
 pub struct PlaybackState {
     pub is_playing: bool,
-    pub current_time: f64, // Seconds
-    pub duration: f64,     // Seconds (Total video length)
+    pub current_time: time::Time, // Sent to the frontend as seconds
+    pub duration: time::Time,     // Total video length
 
     // Internal Sync
     #[serde(skip)]
     pub last_frame_time: Option<std::time::Instant>, // When was the last frame shown? (Wall Clock)
 }
 
-impl Default for PlaybackState {
-    fn default() -> Self {
-        Self {
-            is_playing: false,
-            current_time: 0.0,
-            duration: 0.0,
-            last_frame_time: None,
-        }
-    }
-}
-
 impl KinetixEngine {
     pub fn new() -> Self {
         let instance = Instance::new(&wgpu::InstanceDescriptor::default());
+        let canvas = settings::ProjectSettings::default();
 
         Self {
             instance,
@@ -66,16 +85,23 @@ impl KinetixEngine {
             device: None,
             queue: None,
             render_pipeline: None,
+            background_pipeline: None,
+            target_format: None,
             texture_bind_group_layout: None,
-            texture_bind_group: None,
-            video_texture: None,
-            decoder: None,
+            transform_bind_group_layout: None,
+            source_layer: None,
+            source_preview: false,
+            layers: HashMap::new(),
+            layer_order: Vec::new(),
+            decoder_pool: decoder_pool::DecoderPool::new(),
+            audio: audio::AudioPlayback::new(),
+            frame_cache: frame_cache::FrameCache::default(),
             current_file: None,
             playback_state: PlaybackState::default(),
             timeline_manager: timeline::TimelineManager::new(),
             config: None,
-            width: 1920,
-            height: 1080,
+            width: canvas.width, // Until the window reports its size (see attach_wgpu_renderer)
+            height: canvas.height,
             viewport: None,
         }
     }
@@ -1,14 +1,25 @@
+use super::framing::Framing;
+use super::layer::ClipLayer;
+use super::time::{Time, MICROSECONDS};
+use super::timeline::{Clip, Transform};
+use super::video_frame::{PlaneLayout, VideoFrame};
 use super::KinetixEngine;
 
+// Clips starting this soon get their layer opened and pre-rolled before the cut
+const PREROLL_WINDOW: f64 = 1.0; // Seconds
+
 // Ensure bytemuck is derived
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct TransformUniform {
-    pub position: [f32; 2], // X, Y
+    pub position: [f32; 2],  // X, Y
     pub scale: f32,
     pub rotation: f32,
     pub opacity: f32,
-    pub _padding: [f32; 3], // Necessary for 16-byte alignment in WGSL
+    pub aspect: f32,         // Canvas width / height, keeps rotation square
+    pub size: [f32; 2],      // Framed quad size, canvas fractions (see framing.rs)
+    pub uv_origin: [f32; 2], // Sampled source window
+    pub uv_size: [f32; 2],
 }
 
 impl Default for TransformUniform {
@@ -18,7 +29,48 @@ impl Default for TransformUniform {
             scale: 1.0,
             rotation: 0.0,
             opacity: 1.0,
-            _padding: [0.0; 3],
+            aspect: 16.0 / 9.0,
+            size: Framing::FULL.size,
+            uv_origin: Framing::FULL.uv_origin,
+            uv_size: Framing::FULL.uv_size,
+        }
+    }
+}
+
+impl TransformUniform {
+    pub fn new(transform: &Transform, framing: &Framing, aspect: f32) -> Self {
+        Self {
+            position: transform.position,
+            scale: transform.scale,
+            rotation: transform.rotation,
+            opacity: transform.opacity.clamp(0.0, 1.0),
+            aspect,
+            size: framing.size,
+            uv_origin: framing.uv_origin,
+            uv_size: framing.uv_size,
+        }
+    }
+}
+
+// Must match ColorConversion in video.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ColorUniform {
+    pub rows: [[f32; 4]; 3], // R, G, B as weights of (Y, U, V, 1), see video_frame.rs
+    pub plane_layout: u32,   // 0 = RGBA, 1 = I420, 2 = NV12
+    pub _padding: [u32; 3],
+}
+
+impl ColorUniform {
+    pub fn new(frame: &VideoFrame) -> Self {
+        Self {
+            rows: frame.color.conversion(),
+            plane_layout: match frame.layout {
+                PlaneLayout::Rgba => 0,
+                PlaneLayout::I420 => 1,
+                PlaneLayout::Nv12 => 2,
+            },
+            _padding: [0; 3],
         }
     }
 }
@@ -27,255 +79,341 @@ impl KinetixEngine {
     pub fn load_video(&mut self, path: &str) {
         println!("Engine: Loading Video: {}", path);
 
-        let (Some(device), Some(queue), Some(layout)) =
-            (&self.device, &self.queue, &self.texture_bind_group_layout)
-        else {
+        let (Some(device), Some(layout), Some(transform_layout)) = (
+            &self.device,
+            &self.texture_bind_group_layout,
+            &self.transform_bind_group_layout,
+        ) else {
             println!("Engine: WGPU not ready, skipping load.");
             self.current_file = Some(path.to_string());
             return;
         };
 
-        // 1. Create Decoder
-        let mut decoder = match crate::engine::decoding::VideoDecoder::new(path) {
-            Ok(d) => d,
-            Err(e) => {
-                eprintln!("Failed to create decoder: {}", e);
-                return;
-            }
-        };
-
-        println!(
-            "Engine: Decoder Created. Size: {}x{}",
-            decoder.width(),
-            decoder.height()
-        );
-
-        // 2. Decode First Frame
-        let (frame_data, pts) = match decoder.decode_next_frame() {
-            Ok(d) => d,
+        let layer = match ClipLayer::new(device, layout, transform_layout, path) {
+            Ok(layer) => layer,
             Err(e) => {
-                eprintln!("Failed to decode first frame: {}", e);
+                eprintln!("Failed to load video: {}", e);
                 return;
             }
         };
 
-        // 3. Create Texture
-        let size = wgpu::Extent3d {
-            width: decoder.width(),
-            height: decoder.height(),
-            depth_or_array_layers: 1,
-        };
-
-        let texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("Video Texture"),
-            size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8Unorm, // Compatible with RGBA pixels
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            view_formats: &[],
-        });
-
-        // 4. Upload Data
-        queue.write_texture(
-            wgpu::TexelCopyTextureInfo {
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            &frame_data,
-            wgpu::TexelCopyBufferLayout {
-                offset: 0,
-                bytes_per_row: Some(4 * decoder.width()),
-                rows_per_image: Some(decoder.height()),
-            },
-            size,
-        );
-
-        // 5. Create View & Sampler
-        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::MipmapFilterMode::Nearest,
-            ..Default::default()
-        });
-
-        // 6. Create Bind Group
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
-                },
-            ],
-            label: Some("Video Bind Group"),
-        });
-
-        // 7. Store State
-        self.decoder = Some(decoder);
-        self.texture_bind_group = Some(bind_group);
-        self.video_texture = Some(texture); // Store texture for updates
+        self.playback_state.current_time = layer.pts; // Sync
+        self.source_layer = Some(layer);
+        self.source_preview = true;
         self.current_file = Some(path.to_string());
-        self.playback_state.current_time = pts; // Sync
 
-        // 8. Force Render
+        // The first frame shows once the decode thread has it (see tick)
         self.render();
     }
 
     pub fn play(&mut self) {
+        self.source_preview = false; // The timeline takes over where it has clips
         self.playback_state.is_playing = true;
         self.playback_state.last_frame_time = Some(std::time::Instant::now());
+        self.audio.start(self.playback_state.current_time.seconds());
     }
 
     pub fn pause(&mut self) {
         self.playback_state.is_playing = false;
         self.playback_state.last_frame_time = None;
+        self.audio.stop();
     }
 
-    pub fn seek(&mut self, time: f64) {
+    /// Moves the playhead to the frame nearest `time`.
+    pub fn seek(&mut self, time: Time) {
+        let time = self.timeline_manager.conform_time(time);
+        self.source_preview = false;
         self.playback_state.current_time = time;
         self.playback_state.last_frame_time = Some(std::time::Instant::now());
+        self.audio.reset(time.seconds()); // Audio clock restarts at the new position
         self.sync_video_to_time(time, true); // true = Force Seek
     }
 
-    // Helper: Internal seek that doesn't mess with Global Timeline Time
-    fn seek_decoder_only(&mut self, time: f64) {
-        if let Some(decoder) = &mut self.decoder {
-            if let Err(e) = decoder.seek(time) {
-                eprintln!("Engine Seek Error: {}", e);
-                return;
-            }
-            if let Ok((frame_data, _)) = decoder.decode_next_frame() {
-                self.update_texture(&frame_data);
-            }
+    /// Whether the loaded source file is on screen instead of the timeline: right
+    /// after `load_video` until a seek or play, and wherever the timeline has no video clips.
+    pub fn shows_source(&self, time: Time) -> bool {
+        if self.source_layer.is_none() {
+            return false;
         }
+        let time = time.floor_to(self.timeline_manager.frame_rate());
+        self.source_preview || self.timeline_manager.get_video_clips(time).is_empty()
     }
 
     // Phase 5b: Sync Engine to Timeline
-    fn sync_video_to_time(
-        &mut self,
-        time: f64,
-        force_seek: bool,
-    ) -> Option<crate::engine::timeline::Clip> {
-        // Get all active clips at this time
-        let active_clips = self.timeline_manager.get_active_clips(time);
-
-        // Pick the top-most visible clip (last one in sorted list)
-        let clip = active_clips.last().copied().cloned();
-
-        let clip = clip?;
-
-        // 1. Switch File if needed
-        let needs_load = match &self.current_file {
-            Some(path) => *path != clip.path,
-            None => true,
+    // Keeps one layer per active clip: takes new ones from the warm pool (or opens
+    // them), returns finished ones to it and seeks or advances the rest.
+    // Returns false if nothing is visible.
+    fn sync_video_to_time(&mut self, time: Time, force_seek: bool) -> bool {
+        // Source preview: the loaded file plays on the playhead clock
+        if self.shows_source(time) {
+            let (Some(queue), Some(layer)) = (&self.queue, &mut self.source_layer) else {
+                return false;
+            };
+            if force_seek {
+                layer.seek(queue, &mut self.frame_cache, time);
+            } else {
+                layer.advance_to(queue, &mut self.frame_cache, time);
+            }
+            return true;
+        }
+
+        // The frame on screen at this time; layers show the media under it
+        let time = time.floor_to(self.timeline_manager.frame_rate());
+
+        // Get all clips to draw at this time (video tracks, sorted by Z-Index)
+        let active_clips: Vec<Clip> = self
+            .timeline_manager
+            .get_video_clips(time)
+            .into_iter()
+            .cloned()
+            .collect();
+
+        // 1. Release layers whose clips are no longer visible (kept warm for scrubbing back)
+        let finished: Vec<String> = self
+            .layers
+            .keys()
+            .filter(|id| !active_clips.iter().any(|clip| &clip.id == *id))
+            .cloned()
+            .collect();
+        for id in finished {
+            if let Some(layer) = self.layers.remove(&id) {
+                self.decoder_pool.put(layer);
+            }
+        }
+        self.layer_order = active_clips.iter().map(|clip| clip.id.clone()).collect();
+
+        let (Some(device), Some(queue), Some(layout), Some(transform_layout)) = (
+            &self.device,
+            &self.queue,
+            &self.texture_bind_group_layout,
+            &self.transform_bind_group_layout,
+        ) else {
+            return !active_clips.is_empty();
         };
 
-        if needs_load {
-            println!("Engine: Switching Clip -> {}", clip.path);
-            self.load_video(&clip.path);
-            // load_video renders frame 0. We might be at offset 50.
-            // So we definitely need to seek if offset > 0.
+        // 2. Open or sync a layer per clip
+        for clip in &active_clips {
+            let media_time = clip.get_media_time(time);
+
+            match self.layers.get_mut(&clip.id) {
+                Some(layer) if force_seek => layer.seek(queue, &mut self.frame_cache, media_time),
+                Some(layer) => layer.advance_to(queue, &mut self.frame_cache, media_time),
+                None => {
+                    let layer = match self.decoder_pool.take(&clip.path, media_time) {
+                        Some(layer) => Ok(layer),
+                        None => {
+                            println!("Engine: Opening Layer -> {}", clip.path);
+                            ClipLayer::new(device, layout, transform_layout, &clip.path)
+                        }
+                    };
+                    match layer {
+                        Ok(mut layer) => {
+                            // Pre-rolled layers have this frame ready; new ones start decoding at 0
+                            layer.advance_to(queue, &mut self.frame_cache, media_time);
+                            self.layers.insert(clip.id.clone(), layer);
+                        }
+                        Err(e) => eprintln!("Failed to open layer for {}: {}", clip.path, e),
+                    }
+                }
+            }
         }
 
-        // 2. Calculate Media Time
-        let media_time = (time - clip.start) + clip.offset;
+        !active_clips.is_empty()
+    }
 
-        // 3. Seek if needed
-        // If we just loaded (needs_load), we probably need to seek unless media_time is near 0.
-        // If force_seek (User scrub), we always seek.
-        if force_seek || (needs_load && media_time > 0.1) {
-            self.seek_decoder_only(media_time);
+    pub fn tick(&mut self, dt: f64) {
+        if !self.playback_state.is_playing {
+            // Pick up frames decoded since the last seek, then fill the cache
+            self.sync_video_to_time(self.playback_state.current_time, false);
+            self.prefetch();
+            return;
         }
 
-        Some(clip)
+        // 1. Advance Playhead (audio is the master clock when an output device is open)
+        // The clock runs in microseconds; seeks leave it on a frame, so round up to stay on it
+        self.playback_state.current_time = match self.audio.clock_time() {
+            Some(audio_time) => Time::from_seconds(audio_time, MICROSECONDS),
+            None => {
+                self.playback_state.current_time.ceil_to(MICROSECONDS)
+                    + Time::from_seconds(dt, MICROSECONDS)
+            }
+        };
+        let current_time = self.playback_state.current_time;
+        self.audio.fill(&self.timeline_manager);
+
+        // 2. Sync to Timeline (each layer decodes up to its own media time)
+        // We pass force_seek = false because we want smooth playback, not jump cuts
+        if self.sync_video_to_time(current_time, false) {
+            self.playback_state.last_frame_time = Some(std::time::Instant::now());
+        }
+
+        // 3. Get the next cut ready
+        self.preroll_upcoming(current_time);
     }
 
-    fn update_texture(&self, data: &[u8]) {
-        let (Some(queue), Some(texture)) = (&self.queue, &self.video_texture) else {
+    /// Opens and seeks a layer for a clip starting within `PREROLL_WINDOW`, one per
+    /// tick, so the cut to it neither opens the file nor waits on a seek.
+    fn preroll_upcoming(&mut self, time: Time) {
+        let (Some(device), Some(queue), Some(layout), Some(transform_layout)) = (
+            &self.device,
+            &self.queue,
+            &self.texture_bind_group_layout,
+            &self.transform_bind_group_layout,
+        ) else {
             return;
         };
 
-        // Calculate dimensions (assuming texture size matches data)
-        let width = texture.width();
-        let height = texture.height();
+        let rate = self.timeline_manager.frame_rate();
+        let time = time.floor_to(rate);
+        let horizon = time + Time::from_seconds(PREROLL_WINDOW, rate);
+
+        // Clips starting in (time, horizon], soonest first: short ones may be over by the horizon
+        let upcoming = self
+            .timeline_manager
+            .get_video_clips_in_range(time, horizon + Time::from_frames(1, rate))
+            .into_iter()
+            .filter(|clip| {
+                clip.start > time
+                    && !self.layers.contains_key(&clip.id)
+                    && !self.decoder_pool.is_ready(&clip.path, clip.offset)
+            })
+            .min_by_key(|clip| clip.start)
+            .cloned();
+        let Some(clip) = upcoming else {
+            return;
+        };
 
-        let size = wgpu::Extent3d {
-            width,
-            height,
-            depth_or_array_layers: 1,
+        // Reuse a warm layer on the same file, otherwise open one
+        let layer = match self.decoder_pool.take(&clip.path, clip.offset) {
+            Some(layer) => Ok(layer),
+            None => {
+                println!("Engine: Pre-rolling Layer -> {}", clip.path);
+                ClipLayer::new(device, layout, transform_layout, &clip.path)
+            }
         };
+        match layer {
+            Ok(mut layer) => {
+                layer.seek(queue, &mut self.frame_cache, clip.offset); // Media time at the clip's first frame
+                self.decoder_pool.put(layer);
+            }
+            Err(e) => eprintln!("Failed to pre-roll layer for {}: {}", clip.path, e),
+        }
+    }
 
-        // Upload New Data
-        queue.write_texture(
-            wgpu::TexelCopyTextureInfo {
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            data,
-            wgpu::TexelCopyBufferLayout {
-                offset: 0,
-                bytes_per_row: Some(4 * width),
-                rows_per_image: Some(height),
-            },
-            size,
-        );
+    /// Blocks until every visible layer has settled on its frame at the playhead.
+    /// Headless rendering has no render loop picking up decoded frames. Fails on
+    /// timeout, or if a layer's file can't be decoded, rather than render without it.
+    pub fn wait_for_frames(&mut self, timeout: std::time::Duration) -> anyhow::Result<()> {
+        let time = self.playback_state.current_time;
+        let deadline = std::time::Instant::now() + timeout;
+        loop {
+            self.sync_video_to_time(time, false);
+            if self.frames_settled(time) {
+                break;
+            }
+            if std::time::Instant::now() >= deadline {
+                anyhow::bail!("Timed out waiting for frames at {}", time);
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
 
-        // Note: Render will happen on next frame request (or we could trigger it here if managed)
+        let failures = self.layer_failures(time);
+        if !failures.is_empty() {
+            anyhow::bail!("Can't render {}: {}", time, failures.join("; "));
+        }
+        Ok(())
     }
 
-    pub fn tick(&mut self, dt: f64) {
-        if !self.playback_state.is_playing {
-            return;
+    // Every visible layer shows its frame, ran out of media before it, or failed
+    fn frames_settled(&mut self, time: Time) -> bool {
+        if self.shows_source(time) {
+            return self.source_layer.as_mut().is_none_or(|layer| layer.is_settled(time));
         }
 
-        // 1. Advance Playhead
-        self.playback_state.current_time += dt;
-        let current_time = self.playback_state.current_time;
-
-        // 2. Sync to Timeline (Switch clips if needed)
-        // We pass force_seek = false because we want smooth playback, not jump cuts
-        let active_clip = self.sync_video_to_time(current_time, false);
+        let time = time.floor_to(self.timeline_manager.frame_rate());
+        self.layer_order.iter().all(|id| {
+            match (self.layers.get_mut(id), self.timeline_manager.timeline.clips.get(id)) {
+                (Some(layer), Some(clip)) => layer.is_settled(clip.get_media_time(time)),
+                _ => true, // Failed to open, see layer_failures
+            }
+        })
+    }
 
-        if active_clip.is_none() {
-            return;
+    // Visible layers that can't be decoded, as error messages
+    fn layer_failures(&mut self, time: Time) -> Vec<String> {
+        if self.shows_source(time) {
+            return self
+                .source_layer
+                .as_mut()
+                .and_then(|layer| layer.failure().map(str::to_string))
+                .into_iter()
+                .collect();
         }
 
-        // 3. Decode Next Frame (Pacing)
-        let Some(decoder) = &mut self.decoder else {
-            return;
-        };
+        self.layer_order
+            .iter()
+            .filter_map(|id| {
+                let clip = self.timeline_manager.timeline.clips.get(id)?;
+                match self.layers.get_mut(id) {
+                    Some(layer) => layer.failure().map(str::to_string),
+                    None => Some(format!("Failed to open {}", clip.path)),
+                }
+            })
+            .collect()
+    }
 
-        let now = std::time::Instant::now();
-        let last_time = self.playback_state.last_frame_time.unwrap_or(now);
-        let elapsed = now.duration_since(last_time).as_secs_f64();
+    /// Idle work while paused: fills the frame cache around the playhead, one
+    /// layer step per tick, so scrubbing and frame stepping nearby skip the decoder.
+    fn prefetch(&mut self) {
+        let time = self.playback_state.current_time;
 
-        let fps = decoder.fps;
-        let frame_duration = if fps > 0.0 { 1.0 / fps } else { 0.033 };
+        if self.shows_source(time) {
+            if let Some(layer) = &mut self.source_layer {
+                let frame_bytes = layer.frame_bytes();
+                let radius = self.frame_cache.prefetch_radius(frame_bytes, 1);
+                layer.prefetch(&mut self.frame_cache, time, radius);
+            }
+            return;
+        }
 
-        if elapsed >= frame_duration {
-            match decoder.decode_next_frame() {
-                Ok((frame_data, _pts)) => {
-                    self.update_texture(&frame_data);
-                    self.playback_state.last_frame_time = Some(now);
-                }
-                Err(_) => {
-                    // Start of gap or end of file?
-                }
+        let time = time.floor_to(self.timeline_manager.frame_rate());
+        let layer_count = self.layer_order.len();
+        for id in &self.layer_order {
+            let (Some(layer), Some(clip)) = (self.layers.get_mut(id), self.timeline_manager.timeline.clips.get(id)) else {
+                continue;
+            };
+            let frame_bytes = layer.frame_bytes();
+            let radius = self.frame_cache.prefetch_radius(frame_bytes, layer_count);
+            if layer.prefetch(&mut self.frame_cache, clip.get_media_time(time), radius) {
+                return;
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::timeline::TrackKind;
+
+    #[test]
+    fn test_only_video_tracks_get_layers() {
+        let mut engine = KinetixEngine::new();
+        let rate = engine.timeline_manager.frame_rate();
+        let music = engine.timeline_manager.add_track("Music", TrackKind::Audio);
+        for (id, track_id) in [("picture", 1), ("music", music.id)] {
+            let clip = Clip {
+                id: id.into(),
+                path: format!("{}.mp4", id),
+                duration: Time::from_frames(50, rate),
+                track_id,
+                ..Default::default()
+            };
+            engine.timeline_manager.add_clip(clip).unwrap();
+        }
+
+        // The audio clip is left to the mixer: no video layer is opened for it
+        engine.seek(Time::from_frames(10, rate));
+        assert_eq!(engine.layer_order, ["picture"]);
+    }
+}
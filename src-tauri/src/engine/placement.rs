@@ -0,0 +1,281 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
+
+use super::history::Edit;
+use super::timeline::{Clip, TimelineManager};
+
+/// What to do with clips already on the track where a new clip lands.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlacementPolicy {
+    #[default]
+    Reject, // Fail if anything is in the way
+    Overwrite, // Trim, split or remove what is underneath
+    Insert,    // Split at the insert point and push later clips right
+}
+
+/// Clips changed by placing a clip, so the frontend can update only those.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlacementReport {
+    pub clip_id: String,
+    pub trimmed: Vec<String>,
+    pub split: Vec<String>, // New clips created from the right half of a split
+    pub removed: Vec<String>,
+    pub moved: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum PlacementError {
+    /// Rejected: these clips on the track overlap the new one
+    Overlap {
+        track_id: i32,
+        clip_ids: Vec<String>,
+    },
+    Invalid {
+        message: String,
+    },
+}
+
+impl fmt::Display for PlacementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlacementError::Overlap { track_id, clip_ids } => write!(
+                f,
+                "Clip overlaps {} clip(s) on track {}",
+                clip_ids.len(),
+                track_id
+            ),
+            PlacementError::Invalid { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<String> for PlacementError {
+    fn from(message: String) -> Self {
+        PlacementError::Invalid { message }
+    }
+}
+
+impl TimelineManager {
+    /// Adds a clip, resolving overlaps on its track according to `policy`.
+    /// All changes are recorded as one undo step.
+    pub fn place_clip(
+        &mut self,
+        mut clip: Clip,
+        policy: PlacementPolicy,
+    ) -> Result<PlacementReport, PlacementError> {
+        clip.conform(self.frame_rate());
+        if clip.duration.value <= 0 || clip.start.value < 0 {
+            return Err(format!("Invalid clip range: {} + {}", clip.start, clip.duration).into());
+        }
+        self.check_unlocked(clip.track_id)?;
+
+        // Magnetic tracks always insert, at the closest edit point
+        let policy = if self.is_magnetic(clip.track_id) {
+            clip.start = self.nearest_edit_point(clip.track_id, clip.start);
+            PlacementPolicy::Insert
+        } else {
+            policy
+        };
+
+        // Ensure ID
+        if clip.id.is_empty() {
+            clip.id = Uuid::new_v4().to_string();
+        }
+
+        // Auto-assign Z-Index based on Track ID if not set
+        if clip.z_index == 0 {
+            clip.z_index = clip.track_id;
+        }
+
+        let (start, end) = (clip.start, clip.end());
+        let mut neighbours: Vec<Clip> = self
+            .timeline
+            .clips
+            .values()
+            .filter(|c| c.track_id == clip.track_id && c.id != clip.id)
+            .cloned()
+            .collect();
+        neighbours.sort_by_key(|c| c.start);
+
+        let mut report = PlacementReport {
+            clip_id: clip.id.clone(),
+            ..Default::default()
+        };
+        let mut edits = Vec::new();
+
+        match policy {
+            PlacementPolicy::Reject => {
+                let clip_ids: Vec<String> = neighbours
+                    .iter()
+                    .filter(|c| c.overlaps(start, end))
+                    .map(|c| c.id.clone())
+                    .collect();
+                if !clip_ids.is_empty() {
+                    return Err(PlacementError::Overlap {
+                        track_id: clip.track_id,
+                        clip_ids,
+                    });
+                }
+            }
+            PlacementPolicy::Overwrite => {
+                for before in neighbours.into_iter().filter(|c| c.overlaps(start, end)) {
+                    let covers_start = before.start >= start;
+                    let covers_end = before.end() <= end;
+
+                    let mut after = before.clone();
+                    match (covers_start, covers_end) {
+                        // 1. Fully underneath: remove
+                        (true, true) => {
+                            report.removed.push(before.id.clone());
+                            edits.push(Edit::clip(before.id.clone(), Some(before), None));
+                            continue;
+                        }
+                        // 2. Sticks out both sides: keep left, new clip after the hole
+                        (false, false) => {
+                            let mut right = after.split_off(start);
+                            right.start = end;
+                            right.offset += end - start;
+                            right.duration -= end - start;
+                            report.split.push(right.id.clone());
+                            edits.push(Edit::clip(right.id.clone(), None, Some(right)));
+                        }
+                        // 3. Overlaps our start: trim its tail
+                        (false, true) => after.duration = start - after.start,
+                        // 4. Overlaps our end: trim its head
+                        (true, false) => {
+                            after = after.split_off(end);
+                            after.id = before.id.clone();
+                        }
+                    }
+                    report.trimmed.push(before.id.clone());
+                    edits.push(Edit::clip(before.id.clone(), Some(before), Some(after)));
+                }
+            }
+            PlacementPolicy::Insert => {
+                let shift = clip.duration;
+                for before in neighbours.into_iter().filter(|c| c.end() > start) {
+                    let mut after = before.clone();
+                    if after.start < start {
+                        // Straddles the insert point: split and push the right half
+                        let mut right = after.split_off(start);
+                        right.start += shift;
+                        report.split.push(right.id.clone());
+                        report.trimmed.push(before.id.clone());
+                        edits.push(Edit::clip(right.id.clone(), None, Some(right)));
+                    } else {
+                        after.start += shift;
+                        report.moved.push(before.id.clone());
+                    }
+                    edits.push(Edit::clip(before.id.clone(), Some(before), Some(after)));
+                }
+            }
+        }
+
+        let before = self.timeline.clips.get(&clip.id).cloned();
+        edits.push(Edit::clip(clip.id.clone(), before, Some(clip)));
+        self.commit("Add Clip", edits);
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::time::{Time, DEFAULT_FRAME_RATE};
+
+    fn clip(id: &str, start: f64, duration: f64) -> Clip {
+        Clip {
+            id: id.into(),
+            start: Time::from_seconds(start, DEFAULT_FRAME_RATE),
+            duration: Time::from_seconds(duration, DEFAULT_FRAME_RATE),
+            track_id: 1,
+            ..Default::default()
+        }
+    }
+
+    fn span(manager: &TimelineManager, id: &str) -> (f64, f64, f64) {
+        let c = &manager.timeline.clips[id];
+        (c.start.seconds(), c.duration.seconds(), c.offset.seconds())
+    }
+
+    #[test]
+    fn test_reject_reports_overlaps() {
+        let mut manager = TimelineManager::new();
+        manager.add_clip(clip("a", 0.0, 4.0)).unwrap();
+        manager.add_clip(clip("b", 4.0, 2.0)).unwrap(); // Touching is fine
+
+        match manager.place_clip(clip("c", 3.0, 2.0), PlacementPolicy::Reject) {
+            Err(PlacementError::Overlap {
+                track_id,
+                mut clip_ids,
+            }) => {
+                clip_ids.sort();
+                assert_eq!(track_id, 1);
+                assert_eq!(clip_ids, ["a", "b"]);
+            }
+            other => panic!("expected overlap, got {:?}", other),
+        }
+        assert!(!manager.timeline.clips.contains_key("c"));
+    }
+
+    #[test]
+    fn test_overwrite_trims_and_splits() {
+        let mut manager = TimelineManager::new();
+        manager.add_clip(clip("a", 0.0, 10.0)).unwrap();
+        manager.add_clip(clip("b", 10.0, 2.0)).unwrap();
+
+        let report = manager
+            .place_clip(clip("c", 4.0, 2.0), PlacementPolicy::Overwrite)
+            .unwrap();
+        assert_eq!(report.trimmed, ["a"]);
+        assert_eq!(span(&manager, "a"), (0.0, 4.0, 0.0));
+        assert_eq!(span(&manager, &report.split[0]), (6.0, 4.0, 6.0));
+
+        // Covers the right half of "a" fully and the head of "b"
+        let report = manager
+            .place_clip(clip("d", 5.0, 6.0), PlacementPolicy::Overwrite)
+            .unwrap();
+        let mut trimmed = report.trimmed.clone();
+        trimmed.sort();
+        assert_eq!(trimmed, ["b", "c"]);
+        assert_eq!(report.removed.len(), 1); // The split half
+        assert_eq!(span(&manager, "c"), (4.0, 1.0, 0.0));
+        assert_eq!(span(&manager, "b"), (11.0, 1.0, 1.0));
+
+        // One undo restores everything
+        manager.undo();
+        assert_eq!(manager.timeline.clips.len(), 4);
+    }
+
+    #[test]
+    fn test_insert_ripples_track() {
+        let mut manager = TimelineManager::new();
+        manager.add_clip(clip("a", 0.0, 4.0)).unwrap();
+        manager.add_clip(clip("b", 4.0, 2.0)).unwrap();
+        manager
+            .add_clip(Clip {
+                track_id: 2,
+                ..clip("other", 5.0, 1.0)
+            })
+            .unwrap();
+
+        let report = manager
+            .place_clip(clip("c", 2.0, 3.0), PlacementPolicy::Insert)
+            .unwrap();
+        assert_eq!(report.moved, ["b"]);
+        assert_eq!(span(&manager, "a"), (0.0, 2.0, 0.0));
+        assert_eq!(span(&manager, &report.split[0]), (5.0, 2.0, 2.0));
+        assert_eq!(span(&manager, "b"), (7.0, 2.0, 0.0));
+        assert_eq!(span(&manager, "other"), (5.0, 1.0, 0.0)); // Other tracks stay
+        assert_eq!(manager.timeline.duration.seconds(), 9.0);
+    }
+}
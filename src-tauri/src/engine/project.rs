@@ -0,0 +1,226 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use super::timeline::Timeline;
+
+/// Current project schema version. Bump it and append a migration when the
+/// saved shape of `Timeline` changes.
+pub const PROJECT_VERSION: u32 = 1;
+
+/// Upgrades a project document by one version. `MIGRATIONS[n]` turns
+/// version `n` into version `n + 1`.
+type Migration = fn(&mut Value) -> Result<()>;
+
+const MIGRATIONS: &[Migration] = &[migrate_v0_to_v1];
+
+// v0: a bare Timeline, as produced by serializing it directly
+fn migrate_v0_to_v1(doc: &mut Value) -> Result<()> {
+    let timeline = doc.take();
+    *doc = serde_json::json!({ "version": 1, "timeline": timeline });
+    Ok(())
+}
+
+/// On-disk project file (`.kenichi`).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectFile {
+    pub version: u32,
+    #[serde(default)]
+    pub generator: String, // App version that wrote the file
+    pub timeline: Timeline,
+}
+
+/// Writes the timeline to `path`, storing media paths relative to the project folder.
+pub fn save_project(path: &Path, timeline: &Timeline) -> Result<()> {
+    let base = project_dir(path);
+
+    let mut timeline = timeline.clone();
+    for clip in timeline.clips.values_mut() {
+        if let Some(relative) = relative_to(Path::new(&clip.path), &base) {
+            clip.path = relative.to_string_lossy().replace('\\', "/");
+        }
+    }
+
+    let project = ProjectFile {
+        version: PROJECT_VERSION,
+        generator: format!("kenichi {}", env!("CARGO_PKG_VERSION")),
+        timeline,
+    };
+    let json = serde_json::to_string_pretty(&project)?;
+
+    // Write next to the target and rename, so a failed save never truncates the project
+    let temp_path = path.with_extension("kenichi.tmp");
+    std::fs::write(&temp_path, json)?;
+    std::fs::rename(&temp_path, path)?;
+
+    println!("Project Saved: {:?}", path);
+    Ok(())
+}
+
+/// Reads a project, migrating older versions and resolving media paths.
+pub fn open_project(path: &Path) -> Result<Timeline> {
+    let json = std::fs::read_to_string(path)?;
+    let mut doc: Value = serde_json::from_str(&json)?;
+
+    // Unversioned files are bare v0 timelines
+    let mut version = match doc.get("version") {
+        None => 0,
+        Some(value) => value
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or(anyhow::anyhow!("Invalid project version: {}", value))?,
+    };
+
+    if version > PROJECT_VERSION {
+        anyhow::bail!(
+            "Project was saved by a newer version (schema {}, supported {})",
+            version,
+            PROJECT_VERSION
+        );
+    }
+
+    while version < PROJECT_VERSION {
+        println!("Project: Migrating schema v{} -> v{}", version, version + 1);
+        MIGRATIONS[version as usize](&mut doc)?;
+        version += 1;
+    }
+
+    let project: ProjectFile = serde_json::from_value(doc)?;
+    let mut timeline = project.timeline;
+
+    let base = project_dir(path);
+    for clip in timeline.clips.values_mut() {
+        let media_path = Path::new(&clip.path);
+        if media_path.is_relative() {
+            clip.path = base.join(media_path).to_string_lossy().to_string();
+        }
+    }
+    timeline.conform(); // Times are stored as seconds; back to exact frames
+
+    println!("Project Opened: {:?} ({} clips)", path, timeline.clips.len());
+    Ok(timeline)
+}
+
+fn project_dir(path: &Path) -> PathBuf {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf())
+}
+
+// Only rewrites absolute paths on the same root (drive), so moving the project
+// folder together with its media keeps working.
+fn relative_to(path: &Path, base: &Path) -> Option<PathBuf> {
+    if !path.is_absolute() || !base.is_absolute() {
+        return None;
+    }
+
+    let path_parts: Vec<_> = path.components().collect();
+    let base_parts: Vec<_> = base.components().collect();
+    if path_parts.first() != base_parts.first() {
+        return None;
+    }
+
+    let common = path_parts
+        .iter()
+        .zip(&base_parts)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut relative = PathBuf::new();
+    for _ in common..base_parts.len() {
+        relative.push("..");
+    }
+    for part in &path_parts[common..] {
+        relative.push(part);
+    }
+    Some(relative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::time::{Time, DEFAULT_FRAME_RATE};
+    use crate::engine::timeline::{Clip, TimelineManager};
+
+    // A project folder of its own (per test and process), removed when dropped
+    struct TempProject {
+        dir: PathBuf,
+    }
+
+    impl TempProject {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir()
+                .join(format!("kenichi_project_{}_{}", name, std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            Self { dir }
+        }
+
+        fn path(&self) -> PathBuf {
+            self.dir.join("edit.kenichi")
+        }
+    }
+
+    impl Drop for TempProject {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_uses_relative_media_paths() {
+        let project = TempProject::new("round_trip");
+        let project_path = project.path();
+        let media_path = project_path.parent().unwrap().join("media/clip.mp4");
+
+        let mut manager = TimelineManager::new();
+        manager.add_clip(Clip {
+            id: "a".into(),
+            path: media_path.to_string_lossy().to_string(),
+            name: "clip.mp4".into(),
+            start: Time::from_seconds(1.0, DEFAULT_FRAME_RATE),
+            duration: Time::from_seconds(2.0, DEFAULT_FRAME_RATE),
+            offset: Time::from_seconds(0.5, DEFAULT_FRAME_RATE),
+            track_id: 1,
+            ..Default::default()
+        })
+        .unwrap();
+
+        save_project(&project_path, &manager.timeline).unwrap();
+
+        let saved: Value =
+            serde_json::from_str(&std::fs::read_to_string(&project_path).unwrap()).unwrap();
+        assert_eq!(saved["version"], PROJECT_VERSION);
+        assert_eq!(saved["timeline"]["clips"]["a"]["path"], "media/clip.mp4");
+
+        let timeline = open_project(&project_path).unwrap();
+        assert_eq!(Path::new(&timeline.clips["a"].path), media_path);
+        assert_eq!(timeline.clips["a"].start, Time::from_frames(30, DEFAULT_FRAME_RATE));
+        assert_eq!(timeline.clips["a"].start.rate, DEFAULT_FRAME_RATE); // Conformed, not microseconds
+    }
+
+    #[test]
+    fn test_migrates_unversioned_timeline() {
+        let project = TempProject::new("legacy");
+        let project_path = project.path();
+        let legacy = serde_json::to_string(&Timeline::new()).unwrap();
+        std::fs::write(&project_path, legacy).unwrap();
+
+        let timeline = open_project(&project_path).unwrap();
+        assert_eq!(timeline.tracks.len(), 2);
+    }
+
+    #[test]
+    fn test_rejects_newer_or_invalid_version() {
+        let project = TempProject::new("newer");
+        let project_path = project.path();
+        std::fs::write(&project_path, r#"{ "version": 999, "timeline": {} }"#).unwrap();
+        assert!(open_project(&project_path).is_err());
+
+        // Would truncate to 1 as u32
+        std::fs::write(&project_path, r#"{ "version": 4294967297, "timeline": {} }"#).unwrap();
+        assert!(open_project(&project_path).is_err());
+        std::fs::write(&project_path, r#"{ "version": "2", "timeline": {} }"#).unwrap();
+        assert!(open_project(&project_path).is_err());
+    }
+}
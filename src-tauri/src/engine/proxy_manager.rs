@@ -1,9 +1,16 @@
 use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
+use super::export_utils::{
+    ffmpeg_binary, probe_duration, FfmpegProgress, JobKind, ProgressCallback, ProgressParser,
+    ProgressTracker,
+};
+
 #[derive(Clone)]
 pub struct ProxyManager {
     // Track active jobs to avoid duplicate work
@@ -28,8 +35,8 @@ impl ProxyManager {
     }
 
     /// Returns the path to the proxy file (if ready) or the original source.
-    /// Triggers generation in the background if needed.
-    pub async fn ensure_proxy(&self, source: String) -> String {
+    /// Triggers generation in the background if needed, reporting through `on_progress`.
+    pub async fn ensure_proxy(&self, source: String, on_progress: Option<ProgressCallback>) -> String {
         let proxy_path = self.get_proxy_path(&source);
         
         if proxy_path.exists() {
@@ -52,7 +59,7 @@ impl ProxyManager {
         let source_clone = source.clone();
         
         tauri::async_runtime::spawn(async move {
-            if let Err(e) = manager.generate_proxy(&source_clone).await {
+            if let Err(e) = manager.generate_proxy(&source_clone, on_progress).await {
                 eprintln!("Failed to generate proxy for {}: {}", source_clone, e);
             }
             // Cleanup job marker
@@ -63,36 +70,75 @@ impl ProxyManager {
         source // Return original while waiting
     }
 
-    async fn generate_proxy(&self, source: &str) -> anyhow::Result<()> {
+    // The last progress event is sent once FFmpeg has exited: finished, or failed
+    async fn generate_proxy(
+        &self,
+        source: &str,
+        on_progress: Option<ProgressCallback>,
+    ) -> anyhow::Result<()> {
+        let tracker = ProgressTracker::new(
+            source,
+            JobKind::Proxy,
+            probe_duration(source).unwrap_or(0.0),
+        );
+        let result = self.encode_proxy(source, &tracker, on_progress.as_ref()).await;
+        if let Some(on_progress) = &on_progress {
+            on_progress(match &result {
+                Ok(last) => tracker.report(last.frame, last.fps, last.out_time, true),
+                Err(e) => tracker.fail(e),
+            });
+        }
+        result.map(|_| ())
+    }
+
+    // Runs FFmpeg, forwarding its progress. Returns the last report.
+    async fn encode_proxy(
+        &self,
+        source: &str,
+        tracker: &ProgressTracker,
+        on_progress: Option<&ProgressCallback>,
+    ) -> anyhow::Result<FfmpegProgress> {
         let proxy_path = self.get_proxy_path(source);
         println!("Starting Proxy Generation: {:?}", proxy_path);
 
-        // Resolve Sidecar Path
-        // In dev: src-tauri/bin/ffmpeg...
-        // In prod: handled by Tauri sidecar injection, but we call manual binary here for control
-        let cwd = std::env::current_dir()?;
-        let sidecar_path = cwd.join("src-tauri/bin/ffmpeg-x86_64-pc-windows-msvc.exe");
-        
-        let ffmpeg_cmd = if sidecar_path.exists() {
-            sidecar_path.to_string_lossy().to_string()
-        } else {
-            "ffmpeg".to_string() // Fallback to PATH
-        };
+        let ffmpeg_cmd = ffmpeg_binary();
 
-        let status = Command::new(ffmpeg_cmd)
+        let mut child = Command::new(ffmpeg_cmd)
             .arg("-i").arg(source)
             .arg("-vf").arg("scale=-2:480") // Fixed 480p height
             .arg("-c:v").arg("libx264")
             .arg("-preset").arg("ultrafast")
             .arg("-tune").arg("fastdecode")
+            .arg("-progress").arg("pipe:1") // Machine-readable progress on stdout
+            .arg("-nostats")
             .arg("-y")
             .arg(&proxy_path)
-            .status() // Await the process
-            .await?;
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        // Forward progress while the process runs. The end is reported by the
+        // caller, once the exit status says whether it worked
+        let mut last = FfmpegProgress::default();
+        if let Some(stdout) = child.stdout.take() {
+            let mut parser = ProgressParser::default();
+            let mut lines = BufReader::new(stdout).lines();
+
+            while let Some(line) = lines.next_line().await? {
+                let Some(progress) = parser.feed(&line) else {
+                    continue;
+                };
+                last = progress;
+                if let Some(on_progress) = on_progress.filter(|_| !progress.is_end) {
+                    on_progress(tracker.report_ffmpeg(&progress));
+                }
+            }
+        }
+
+        let status = child.wait().await?;
 
         if status.success() {
             println!("Proxy Complete: {:?}", proxy_path);
-            Ok(())
+            Ok(last)
         } else {
             anyhow::bail!("FFmpeg exited with error code")
         }
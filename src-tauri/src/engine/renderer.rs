@@ -1,5 +1,21 @@
+use std::time::Duration;
+
+use super::layer::ClipLayer;
+use super::framing::Framing;
+use super::model::TransformUniform;
+use super::time::Time;
+use super::timeline::Transform;
 use super::KinetixEngine;
 
+// Headless render target (export, tests). Not sRGB: frames are uploaded sRGB-encoded
+// and written back unchanged, so export encodes the same values preview shows
+const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+// How long `render_frame_at` / `render_next_frame` wait for decode threads to deliver the frame.
+// Layers that failed or ran out of media don't hold them up
+const FRAME_TIMEOUT: Duration = Duration::from_secs(5);
+// Around the canvas when its aspect differs from the target (preview viewport, export size)
+const LETTERBOX_COLOR: wgpu::Color = wgpu::Color::BLACK;
+
 impl KinetixEngine {
     pub async fn init_surface<W>(&mut self, window: W) -> anyhow::Result<()>
     where
@@ -26,23 +42,14 @@ impl KinetixEngine {
         println!("GPU Adapter: {:?}", adapter.get_info());
 
         // 3. Request Device & Queue
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: Some("Kenichi Device"),
-                    required_features: wgpu::Features::empty(),
-                    required_limits: wgpu::Limits::downlevel_webgl2_defaults(),
-                    memory_hints: wgpu::MemoryHints::default(),
-                    ..Default::default()
-                },
-            )
-            .await?;
+        let (device, queue) = Self::request_device(&adapter).await?;
 
         // 4. Configure Surface
         let caps = surface.get_capabilities(&adapter);
+        // Non-sRGB like the offscreen target: an sRGB one would encode the frames a second time
         let format = caps.formats.iter()
             .copied()
-            .find(|f| f.is_srgb())
+            .find(|f| !f.is_srgb())
             .unwrap_or(caps.formats[0]);
 
         let config = wgpu::SurfaceConfiguration {
@@ -59,10 +66,67 @@ impl KinetixEngine {
         surface.configure(&device, &config);
 
         self.surface = Some(surface);
+        self.config = Some(config);
+        self.init_pipeline(adapter, device, queue, format);
+
+        // Initial Render
+        self.render();
+
+        println!("WGPU Surface Initialized Successfully");
+        Ok(())
+    }
+
+    /// Headless mode: a GPU without a window, for export, thumbnails and tests.
+    /// Prefers a hardware adapter and falls back to a software one. Frames are
+    /// read back with `render_to_rgba` / `render_frame_at`; `render` does nothing.
+    pub async fn init_headless(&mut self) -> anyhow::Result<()> {
+        println!("Initializing Headless WGPU...");
+
+        let options = |force_fallback_adapter| wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: None,
+            force_fallback_adapter,
+        };
+        let adapter = match self.instance.request_adapter(&options(false)).await {
+            Ok(adapter) => adapter,
+            Err(_) => {
+                println!("No hardware adapter, falling back to software rendering");
+                self.instance
+                    .request_adapter(&options(true))
+                    .await
+                    .map_err(|_| anyhow::anyhow!("Failed to find a headless adapter"))?
+            }
+        };
+        println!("GPU Adapter: {:?}", adapter.get_info());
+
+        let (device, queue) = Self::request_device(&adapter).await?;
+        self.init_pipeline(adapter, device, queue, OFFSCREEN_FORMAT);
+
+        println!("Headless WGPU Initialized Successfully");
+        Ok(())
+    }
+
+    async fn request_device(adapter: &wgpu::Adapter) -> anyhow::Result<(wgpu::Device, wgpu::Queue)> {
+        Ok(adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("Kenichi Device"),
+                    required_features: wgpu::Features::empty(),
+                    // Texture size limits from the adapter, so 4K sources and outputs fit
+                    required_limits: wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits()),
+                    memory_hints: wgpu::MemoryHints::default(),
+                    ..Default::default()
+                },
+            )
+            .await?)
+    }
+
+    // Bind group layouts, shader and pipeline drawing into `format`
+    fn init_pipeline(&mut self, adapter: wgpu::Adapter, device: wgpu::Device, queue: wgpu::Queue, format: wgpu::TextureFormat) {
         self.adapter = Some(adapter);
         self.device = Some(device);
         self.queue = Some(queue);
-        self.config = Some(config);
+        self.target_format = Some(format);
 
         // 5. Create Texture Bind Group Layout
         let texture_bind_group_layout = self.device.as_ref().unwrap().create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -84,9 +148,56 @@ impl KinetixEngine {
                     ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
                     count: None,
                 },
+                // Chroma planes (U / V, or interleaved UV in binding 2)
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // ColorUniform: plane layout and YUV -> RGB matrix
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
             ],
         });
         
+        // 5b. Create Transform Bind Group Layout (one uniform per layer)
+        let transform_bind_group_layout = self.device.as_ref().unwrap().create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Transform Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
         // 6. Create Shader Module
         let shader = self.device.as_ref().unwrap().create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Video Shader"),
@@ -96,7 +207,7 @@ impl KinetixEngine {
         // 7. Create Pipeline Layout
         let pipeline_layout = self.device.as_ref().unwrap().create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Video Pipeline Layout"),
-            bind_group_layouts: &[&texture_bind_group_layout],
+            bind_group_layouts: &[&texture_bind_group_layout, &transform_bind_group_layout],
             ..Default::default()
         });
 
@@ -115,7 +226,7 @@ impl KinetixEngine {
                 entry_point: Some("fs_main"),
                 targets: &[Some(wgpu::ColorTargetState {
                     format: format,
-                    blend: Some(wgpu::BlendState::REPLACE),
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING), // Layers composite over each other
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
                 compilation_options: wgpu::PipelineCompilationOptions::default(),
@@ -124,7 +235,7 @@ impl KinetixEngine {
                 topology: wgpu::PrimitiveTopology::TriangleList,
                 strip_index_format: None,
                 front_face: wgpu::FrontFace::Ccw,
-                cull_mode: None, // Disable culling to ensure full-screen triangle is visible
+                cull_mode: None, // Disable culling so flipped (negative scale) quads stay visible
                 polygon_mode: wgpu::PolygonMode::Fill,
                 unclipped_depth: false,
                 conservative: false,
@@ -139,14 +250,54 @@ impl KinetixEngine {
             multiview_mask: None, 
         });
 
+        // 9. Background pipeline: one triangle over the viewport (the canvas rect) in
+        // the blend constant, so the letterbox around the canvas keeps the clear color
+        let background_shader = self.device.as_ref().unwrap().create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Background Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../../../src/lib/shaders/background.wgsl").into()),
+        });
+        let background_layout = self.device.as_ref().unwrap().create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Background Pipeline Layout"),
+            ..Default::default()
+        });
+        let constant = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::Constant,
+            dst_factor: wgpu::BlendFactor::Zero,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let background_pipeline = self.device.as_ref().unwrap().create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Background Render Pipeline"),
+            layout: Some(&background_layout),
+            vertex: wgpu::VertexState {
+                module: &background_shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &background_shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState {
+                        color: constant,
+                        alpha: constant,
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            cache: None,
+            multiview_mask: None,
+        });
+
         self.render_pipeline = Some(pipeline);
+        self.background_pipeline = Some(background_pipeline);
         self.texture_bind_group_layout = Some(texture_bind_group_layout);
-
-        // Initial Render
-        self.render();
-
-        println!("WGPU Surface Initialized Successfully");
-        Ok(())
+        self.transform_bind_group_layout = Some(transform_bind_group_layout);
     }
 
     pub fn update_viewport(&mut self, x: f32, y: f32, width: f32, height: f32) {
@@ -173,9 +324,22 @@ impl KinetixEngine {
         }
     }
 
+    /// Where the canvas is drawn: the viewport (or the whole surface), shrunk to the
+    /// project's display aspect and centered.
+    fn canvas_rect(&self) -> Option<[f32; 4]> {
+        let area = match (self.viewport, &self.config) {
+            (Some(vp), _) if vp[2] > 0.0 && vp[3] > 0.0 => vp,
+            (_, Some(config)) => [0.0, 0.0, config.width as f32, config.height as f32],
+            _ => return None,
+        };
+
+        Some(fit_canvas(area, self.timeline_manager.settings().display_aspect()))
+    }
+
     pub fn render(&mut self) {
-        let (Some(surface), Some(device), Some(queue), Some(pipeline)) = (&self.surface, &self.device, &self.queue, &self.render_pipeline) else {
-            return;
+        let canvas = self.canvas_rect();
+        let Some(surface) = &self.surface else {
+            return; // Headless: see render_to_rgba
         };
 
         // Get Surface Texture
@@ -189,24 +353,146 @@ impl KinetixEngine {
         };
 
         let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.draw(&view, canvas);
+        frame.present();
+    }
+
+    /// Renders the current frame into an offscreen `width` x `height` texture and
+    /// reads it back as tightly packed RGBA (sRGB). The canvas is letterboxed into
+    /// it if the project aspect differs. Works with or without a surface.
+    pub fn render_to_rgba(&self, width: u32, height: u32) -> anyhow::Result<Vec<u8>> {
+        let (Some(device), Some(queue), Some(format)) = (&self.device, &self.queue, self.target_format) else {
+            anyhow::bail!("GPU not initialized (call init_surface or init_headless)");
+        };
+        if width == 0 || height == 0 {
+            anyhow::bail!("Render size must be non-zero, got {}x{}", width, height);
+        }
+
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format, // Same as the pipeline target
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let area = [0.0, 0.0, width as f32, height as f32];
+        self.draw(&view, Some(fit_canvas(area, self.timeline_manager.settings().display_aspect())));
+
+        // Rows are padded to 256 bytes for the copy
+        let row_bytes = width * 4;
+        let padded_row_bytes = row_bytes.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: u64::from(padded_row_bytes) * u64::from(height),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Readback Encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: Some(height),
+                },
+            },
+            size,
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+
+        // Map and wait (blocking: callers are off the UI thread)
+        let slice = buffer.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        device.poll(wgpu::PollType::wait_indefinitely())?;
+        rx.recv()??;
+
+        let mut pixels = Vec::with_capacity((row_bytes * height) as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks_exact(padded_row_bytes as usize) {
+                pixels.extend_from_slice(&row[..row_bytes as usize]);
+            }
+        }
+        buffer.unmap();
+
+        // Surface formats are often BGRA
+        if matches!(format, wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb) {
+            pixels.chunks_exact_mut(4).for_each(|px| px.swap(0, 2));
+        }
+        Ok(pixels)
+    }
+
+    /// Seeks to `time`, waits for every visible layer to have its frame decoded
+    /// and renders it offscreen at the project canvas size. Fails if a layer's
+    /// file can't be decoded or its frame doesn't arrive in time.
+    pub fn render_frame_at(&mut self, time: Time) -> anyhow::Result<Vec<u8>> {
+        self.seek(time);
+        self.wait_for_frames(FRAME_TIMEOUT)?;
+        let settings = *self.timeline_manager.settings();
+        self.render_to_rgba(settings.width, settings.height)
+    }
+
+    /// Renders `time` at `width` x `height` for callers stepping through the timeline
+    /// in order (export): layers decode on from where they are instead of seeking.
+    /// Waits for every visible layer to have its frame and fails like `render_frame_at`.
+    pub fn render_next_frame(&mut self, time: Time, width: u32, height: u32) -> anyhow::Result<Vec<u8>> {
+        self.playback_state.current_time = time;
+        self.wait_for_frames(FRAME_TIMEOUT)?;
+        self.render_to_rgba(width, height)
+    }
+
+    // Composites every visible layer into `view`, inside `canvas` if given
+    fn draw(&self, view: &wgpu::TextureView, canvas: Option<[f32; 4]>) {
+        let settings = *self.timeline_manager.settings();
+        let srgb_target = self.target_format.is_some_and(|f| f.is_srgb());
+
+        let (Some(device), Some(queue), Some(pipeline), Some(background)) =
+            (&self.device, &self.queue, &self.render_pipeline, &self.background_pipeline)
+        else {
+            return;
+        };
 
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("Render Encoder"),
         });
 
+        // Project background; sRGB targets expect linear values and encode on write
+        let [r, g, b, a] = settings.background_color.map(|c| {
+            let c = f64::from(c);
+            if srgb_target { srgb_to_linear(c) } else { c }
+        });
+
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1, // Dark Gray Background
-                            g: 0.1,
-                            b: 0.1,
-                            a: 1.0, 
-                        }),
+                        load: wgpu::LoadOp::Clear(LETTERBOX_COLOR), // Outside the canvas
                         store: wgpu::StoreOp::Store,
                     },
                     depth_slice: None,
@@ -217,21 +503,81 @@ impl KinetixEngine {
                 multiview_mask: None,
             });
 
-            render_pass.set_pipeline(pipeline);
-            
-            // Apply Viewport if set
-            if let Some(vp) = self.viewport {
-                render_pass.set_viewport(vp[0], vp[1], vp[2], vp[3], 0.0, 1.0);
+            // Draw into the canvas rect (project aspect, inside the viewport if set)
+            if let Some(rect) = canvas {
+                render_pass.set_viewport(rect[0], rect[1], rect[2], rect[3], 0.0, 1.0);
             }
 
-            // Bind Texture if available
-            if let Some(bind_group) = &self.texture_bind_group {
+            // Project background fills the canvas only
+            render_pass.set_pipeline(background);
+            render_pass.set_blend_constant(wgpu::Color { r, g, b, a });
+            render_pass.draw(0..3, 0..1);
+
+            render_pass.set_pipeline(pipeline);
+
+            // Canvas aspect for rotation
+            let aspect = settings.display_aspect();
+
+            // Keyframes are evaluated on the frame shown, not between frames
+            let frame_time = self.playback_state.current_time.floor_to(self.timeline_manager.frame_rate());
+
+            // Draw layers bottom to top (Painter's Algorithm)
+            // The source preview replaces the timeline while it shows (see `shows_source`)
+            let layers: Vec<(&ClipLayer, Transform)> =
+                if self.shows_source(self.playback_state.current_time) {
+                    self.source_layer
+                        .iter()
+                        .map(|layer| (layer, Transform::default()))
+                        .collect()
+                } else {
+                    self.layer_order
+                        .iter()
+                        .filter_map(|id| {
+                            let layer = self.layers.get(id)?;
+                            let clip = self.timeline_manager.timeline.clips.get(id)?;
+                            Some((layer, clip.transform_at(frame_time)))
+                        })
+                        .collect()
+                };
+
+            for (layer, transform) in layers {
+                // Layers still waiting for their first frame aren't drawn
+                let (Some(bind_group), Some((width, height))) = (layer.bind_group(), layer.source_size()) else {
+                    continue;
+                };
+                // Fit / fill / stretch / none against the source frame size
+                let framing = Framing::new(&transform, width, height, &settings);
+                layer.set_transform(queue, &TransformUniform::new(&transform, &framing, aspect));
                 render_pass.set_bind_group(0, bind_group, &[]);
-                render_pass.draw(0..3, 0..1); // Draw 3 vertices for Full-Screen Triangle
+                render_pass.set_bind_group(1, &layer.transform_bind_group, &[]);
+                render_pass.draw(0..6, 0..1); // Draw 6 vertices for the transformed quad
             }
         }
-        
+
         queue.submit(std::iter::once(encoder.finish()));
-        frame.present();
+    }
+}
+
+// `area` shrunk to `aspect` and centered
+fn fit_canvas(area: [f32; 4], aspect: f32) -> [f32; 4] {
+    let (mut width, mut height) = (area[2], area[3]);
+    if width / height > aspect {
+        width = height * aspect; // Pillarbox
+    } else {
+        height = width / aspect; // Letterbox
+    }
+    [
+        area[0] + (area[2] - width) / 2.0,
+        area[1] + (area[3] - height) / 2.0,
+        width,
+        height,
+    ]
+}
+
+fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
     }
 }
@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+
+use super::history::Edit;
+use super::time::{Rational, DEFAULT_FRAME_RATE};
+use super::timeline::{Marker, TimelineManager};
+
+// Largest canvas side we accept (GPU texture limits, encoder support)
+pub const MAX_CANVAS_SIZE: u32 = 8192;
+
+/// Color space the canvas is mastered in. Exports are tagged with it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorSpace {
+    Bt601,
+    #[default]
+    Bt709,
+    Bt2020,
+}
+
+impl ColorSpace {
+    /// FFmpeg names for (colorspace, color_primaries, color_trc).
+    pub fn ffmpeg_tags(&self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            ColorSpace::Bt601 => ("smpte170m", "smpte170m", "smpte170m"),
+            ColorSpace::Bt709 => ("bt709", "bt709", "bt709"),
+            ColorSpace::Bt2020 => ("bt2020nc", "bt2020", "bt2020-10"),
+        }
+    }
+}
+
+/// Sequence settings: the canvas every clip is composited onto.
+/// Preview, snapping and export all read them from the timeline.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ProjectSettings {
+    pub width: u32, // Canvas size in pixels
+    pub height: u32,
+    pub frame_rate: Rational,       // Clip edges, markers and the snap grid sit on these frames
+    pub pixel_aspect: Rational,     // Pixel width / height (1 = square, 4/3 = HDV anamorphic)
+    pub background_color: [f32; 4], // sRGB RGBA, 0.0 - 1.0. Shows through gaps and transparent clips
+    pub color_space: ColorSpace,
+}
+
+impl Default for ProjectSettings {
+    fn default() -> Self {
+        Self {
+            width: 1920,
+            height: 1080,
+            frame_rate: DEFAULT_FRAME_RATE,
+            pixel_aspect: Rational::new(1, 1),
+            background_color: [0.0, 0.0, 0.0, 1.0],
+            color_space: ColorSpace::default(),
+        }
+    }
+}
+
+impl ProjectSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        // yuv420p export needs even dimensions
+        if self.width == 0
+            || self.height == 0
+            || !self.width.is_multiple_of(2)
+            || !self.height.is_multiple_of(2)
+        {
+            return Err(format!(
+                "Canvas size must be non-zero and even, got {}x{}",
+                self.width, self.height
+            ));
+        }
+        if self.width > MAX_CANVAS_SIZE || self.height > MAX_CANVAS_SIZE {
+            return Err(format!(
+                "Canvas size is limited to {}x{}",
+                MAX_CANVAS_SIZE, MAX_CANVAS_SIZE
+            ));
+        }
+        if self.frame_rate.num <= 0 || self.frame_rate.den <= 0 {
+            return Err(format!("Invalid frame rate: {}", self.frame_rate));
+        }
+        if self.pixel_aspect.num <= 0 || self.pixel_aspect.den <= 0 {
+            return Err(format!("Invalid pixel aspect: {}", self.pixel_aspect));
+        }
+        if !self
+            .background_color
+            .iter()
+            .all(|c| (0.0..=1.0).contains(c))
+        {
+            return Err("Background color components must be between 0 and 1".to_string());
+        }
+        Ok(())
+    }
+
+    /// Width / height of the canvas as displayed, with the pixel aspect applied.
+    pub fn display_aspect(&self) -> f32 {
+        let width = f64::from(self.width) * self.pixel_aspect.as_f64();
+        (width / f64::from(self.height.max(1))) as f32
+    }
+}
+
+impl TimelineManager {
+    pub fn settings(&self) -> &ProjectSettings {
+        &self.timeline.settings
+    }
+
+    /// Changes the project settings as one undo step. A new frame rate moves every
+    /// clip edge and marker to the nearest frame of the new rate.
+    pub fn set_settings(&mut self, settings: ProjectSettings) -> Result<(), String> {
+        settings.validate()?;
+        let before = self.timeline.settings;
+        if settings == before {
+            return Ok(());
+        }
+
+        let mut edits = vec![Edit::Settings {
+            before,
+            after: settings,
+        }];
+
+        if settings.frame_rate != before.frame_rate {
+            let rate = settings.frame_rate;
+            for clip in self.timeline.clips.values() {
+                let mut after = clip.clone();
+                after.conform(rate);
+                edits.push(Edit::clip(clip.id.clone(), Some(clip.clone()), Some(after)));
+            }
+            for marker in &self.timeline.markers {
+                let after = Marker {
+                    time: marker.time.rescale(rate),
+                    ..marker.clone()
+                };
+                edits.push(Edit::Marker {
+                    id: marker.id.clone(),
+                    before: Some(marker.clone()),
+                    after: Some(after),
+                });
+            }
+        }
+
+        self.commit("Project Settings", edits);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::time::Time;
+    use crate::engine::timeline::Clip;
+
+    #[test]
+    fn test_frame_rate_change_reconforms_clips() {
+        let mut manager = TimelineManager::new();
+        manager
+            .add_clip(Clip {
+                id: "a".into(),
+                start: Time::from_frames(10, DEFAULT_FRAME_RATE),
+                duration: Time::from_frames(45, DEFAULT_FRAME_RATE),
+                track_id: 1,
+                ..Default::default()
+            })
+            .unwrap();
+
+        let film = Rational::new(24000, 1001);
+        manager
+            .set_settings(ProjectSettings {
+                frame_rate: film,
+                ..Default::default()
+            })
+            .unwrap();
+
+        // 10/30s -> 7.99 frames, 55/30s -> 43.96 frames at 23.976
+        let clip = &manager.timeline.clips["a"];
+        assert_eq!(clip.start, Time::from_frames(8, film));
+        assert_eq!(clip.end(), Time::from_frames(44, film));
+        assert_eq!(manager.frame_rate(), film);
+
+        manager.undo();
+        assert_eq!(manager.frame_rate(), DEFAULT_FRAME_RATE);
+        assert_eq!(
+            manager.timeline.clips["a"].start,
+            Time::from_frames(10, DEFAULT_FRAME_RATE)
+        );
+    }
+
+    #[test]
+    fn test_validation() {
+        let odd = ProjectSettings {
+            width: 1921,
+            ..Default::default()
+        };
+        assert!(odd.validate().is_err());
+
+        let anamorphic = ProjectSettings {
+            width: 1440,
+            pixel_aspect: Rational::new(4, 3),
+            ..Default::default()
+        };
+        assert!(anamorphic.validate().is_ok());
+        assert!((anamorphic.display_aspect() - 16.0 / 9.0).abs() < 1e-6);
+
+        let json = r#"{ "width": 1280, "height": 720, "frameRate": "30000/1001" }"#;
+        let parsed: ProjectSettings = serde_json::from_str(json).unwrap();
+        assert_eq!(parsed.frame_rate, Rational::new(30000, 1001));
+        assert_eq!(parsed.color_space, ColorSpace::Bt709);
+    }
+}
@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+
+use super::time::Time;
+use super::timeline::TimelineManager;
+
+/// What a drag may snap to. Sent by the frontend; everything is on by default.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SnapOptions {
+    pub clip_edges: bool,
+    pub playhead: bool,
+    pub markers: bool,
+    pub frames: bool, // Timeline frame rate grid
+    pub exclude_clips: Vec<String>, // Clips being dragged never snap to themselves
+}
+
+impl Default for SnapOptions {
+    fn default() -> Self {
+        Self {
+            clip_edges: true,
+            playhead: true,
+            markers: true,
+            frames: true,
+            exclude_clips: Vec::new(),
+        }
+    }
+}
+
+/// Snap targets resolved against the engine state.
+#[derive(Clone, Debug)]
+pub struct SnapTargets {
+    pub options: SnapOptions,
+    pub playhead: Time,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SnapKind {
+    None, // Nothing in range, time is unchanged
+    Playhead,
+    Marker,
+    ClipStart,
+    ClipEnd,
+    Frame,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapResult {
+    pub time: Time,
+    pub kind: SnapKind,
+    pub target_id: Option<String>, // Clip or marker ID
+}
+
+impl TimelineManager {
+    /// Snaps `candidate` to the closest target within `threshold`.
+    /// Ties go to the playhead, then markers, then clip edges. Without a
+    /// target in range the time falls back to the nearest frame boundary.
+    pub fn snap_time(&self, candidate: Time, threshold: Time, targets: &SnapTargets) -> SnapResult {
+        let options = &targets.options;
+        let mut best: Option<(Time, SnapResult)> = None;
+
+        let mut consider = |time: Time, kind: SnapKind, target_id: Option<&str>| {
+            // At the candidate's rate, so sub-frame drag positions are measured exactly
+            let distance = (candidate - time).abs();
+            if distance > threshold {
+                return;
+            }
+            // Strictly closer wins, so earlier (higher priority) targets keep ties
+            if best.as_ref().is_some_and(|(d, _)| distance >= *d) {
+                return;
+            }
+            best = Some((
+                distance,
+                SnapResult {
+                    time,
+                    kind,
+                    target_id: target_id.map(str::to_string),
+                },
+            ));
+        };
+
+        // 1. Playhead
+        if options.playhead {
+            consider(targets.playhead, SnapKind::Playhead, None);
+        }
+
+        // 2. Markers
+        if options.markers {
+            for marker in &self.timeline.markers {
+                consider(marker.time, SnapKind::Marker, Some(&marker.id));
+            }
+        }
+
+        // 3. Clip edges
+        if options.clip_edges {
+            let clips = self
+                .timeline
+                .clips
+                .values()
+                .filter(|c| !options.exclude_clips.contains(&c.id));
+            for clip in clips {
+                consider(clip.start, SnapKind::ClipStart, Some(&clip.id));
+                consider(clip.end(), SnapKind::ClipEnd, Some(&clip.id));
+            }
+        }
+
+        if let Some((_, result)) = best {
+            return result;
+        }
+
+        // 4. Frame grid
+        if options.frames {
+            return SnapResult {
+                time: self.conform_time(candidate),
+                kind: SnapKind::Frame,
+                target_id: None,
+            };
+        }
+
+        SnapResult {
+            time: candidate,
+            kind: SnapKind::None,
+            target_id: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::time::{Rational, MICROSECONDS};
+    use crate::engine::timeline::Clip;
+
+    // Drag positions arrive as float seconds, between frames
+    fn t(seconds: f64) -> Time {
+        Time::from_seconds(seconds, MICROSECONDS)
+    }
+
+    fn targets(playhead: f64) -> SnapTargets {
+        SnapTargets {
+            options: SnapOptions::default(),
+            playhead: t(playhead),
+        }
+    }
+
+    #[test]
+    fn test_snap_priorities() {
+        let mut manager = TimelineManager::new();
+        manager.timeline.settings.frame_rate = Rational::new(25, 1);
+        manager
+            .add_clip(Clip {
+                id: "a".into(),
+                start: t(2.0),
+                duration: t(3.0),
+                track_id: 1,
+                ..Default::default()
+            })
+            .unwrap();
+        let marker = manager.add_marker(t(5.0), "Chorus").unwrap();
+
+        // Clip edge
+        let result = manager.snap_time(t(2.05), t(0.1), &targets(10.0));
+        assert_eq!((result.time, result.kind), (t(2.0), SnapKind::ClipStart));
+
+        // Marker and clip end at the same time: marker wins the tie
+        let result = manager.snap_time(t(4.96), t(0.1), &targets(10.0));
+        assert_eq!(result.kind, SnapKind::Marker);
+        assert_eq!(result.target_id, Some(marker));
+
+        // Closer target wins over priority
+        let result = manager.snap_time(t(2.02), t(0.1), &targets(2.1));
+        assert_eq!(result.kind, SnapKind::ClipStart);
+
+        // Nothing in range: nearest frame at 25 fps
+        let result = manager.snap_time(t(7.01), t(0.1), &targets(0.0));
+        assert_eq!((result.time, result.kind), (t(7.0), SnapKind::Frame));
+        assert_eq!(result.time, Time::from_frames(175, Rational::new(25, 1)));
+
+        // Dragged clip is ignored
+        let mut dragging = targets(10.0);
+        dragging.options.exclude_clips = vec!["a".into()];
+        dragging.options.frames = false;
+        let result = manager.snap_time(t(2.05), t(0.1), &dragging);
+        assert_eq!((result.time, result.kind), (t(2.05), SnapKind::None));
+    }
+}
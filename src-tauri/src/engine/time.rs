@@ -0,0 +1,413 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+/// Exact ratio, used for frame rates and timebases (e.g. 24000/1001).
+/// Always reduced with a positive denominator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Rational {
+    pub num: i64,
+    pub den: i64,
+}
+
+/// Frame rate new timelines use (matches the frontend `fps` store).
+pub const DEFAULT_FRAME_RATE: Rational = Rational { num: 30, den: 1 };
+
+/// Microsecond ticks. Times coming in as float seconds (frontend, audio clock)
+/// use this until they're conformed to a frame rate.
+pub const MICROSECONDS: Rational = Rational {
+    num: 1_000_000,
+    den: 1,
+};
+
+impl Rational {
+    pub fn new(num: i64, den: i64) -> Self {
+        assert!(den != 0, "Rational with zero denominator");
+        let divisor = gcd(num, den).max(1) * den.signum();
+        Self {
+            num: num / divisor,
+            den: den / divisor,
+        }
+    }
+
+    pub fn as_f64(self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    /// Best guess at the exact rate behind a float, e.g. 29.97 -> 30000/1001.
+    pub fn from_f64(value: f64) -> Self {
+        if !value.is_finite() || value <= 0.0 {
+            return DEFAULT_FRAME_RATE;
+        }
+
+        // 1. Whole rates (24, 25, 30, 60...)
+        let whole = value.round();
+        if (value - whole).abs() < 1e-3 {
+            return Rational::new(whole as i64, 1);
+        }
+
+        // 2. NTSC rates (23.976, 29.97, 59.94...)
+        let ntsc = (value * 1.001).round();
+        if (value - ntsc / 1.001).abs() < 1e-3 {
+            return Rational::new(ntsc as i64 * 1000, 1001);
+        }
+
+        // 3. Anything else, to the millisecond
+        Rational::new((value * 1000.0).round() as i64, 1000)
+    }
+}
+
+impl Default for Rational {
+    fn default() -> Self {
+        DEFAULT_FRAME_RATE
+    }
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+impl FromStr for Rational {
+    type Err = String;
+
+    /// Parses "24000/1001", "25" or "29.97".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid rate: {}", s);
+        match s.split_once('/') {
+            Some((num, den)) => {
+                let num: i64 = num.trim().parse().map_err(|_| invalid())?;
+                let den: i64 = den.trim().parse().map_err(|_| invalid())?;
+                if num <= 0 || den <= 0 {
+                    return Err(invalid());
+                }
+                Ok(Rational::new(num, den))
+            }
+            None => {
+                let value: f64 = s.trim().parse().map_err(|_| invalid())?;
+                if !value.is_finite() || value <= 0.0 {
+                    return Err(invalid());
+                }
+                Ok(Rational::from_f64(value))
+            }
+        }
+    }
+}
+
+// Serialized as "24000/1001" (or "25"); also accepts a plain number
+impl Serialize for Rational {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Rational {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Text(String),
+            Number(f64),
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Text(text) => text.parse().map_err(serde::de::Error::custom),
+            Repr::Number(value) if value.is_finite() && value > 0.0 => {
+                Ok(Rational::from_f64(value))
+            }
+            Repr::Number(value) => {
+                Err(serde::de::Error::custom(format!("Invalid rate: {}", value)))
+            }
+        }
+    }
+}
+
+/// A point or length in time: `value` ticks at `rate` ticks per second.
+/// On the timeline the rate is the frame rate, so `value` is a frame count
+/// and edits land exactly on frames (no drift at 23.976 or 29.97).
+///
+/// Crosses the JSON boundary as float seconds, like the rest of the frontend API;
+/// the timeline conforms incoming times back to whole frames.
+#[derive(Clone, Copy, Debug)]
+pub struct Time {
+    pub value: i64,
+    pub rate: Rational,
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Time::zero(DEFAULT_FRAME_RATE)
+    }
+}
+
+impl Time {
+    pub const fn zero(rate: Rational) -> Self {
+        Self { value: 0, rate }
+    }
+
+    pub fn from_frames(frames: i64, rate: Rational) -> Self {
+        Self {
+            value: frames,
+            rate,
+        }
+    }
+
+    /// Nearest tick to `seconds`.
+    pub fn from_seconds(seconds: f64, rate: Rational) -> Self {
+        let ticks = seconds * rate.num as f64 / rate.den as f64;
+        Self {
+            value: ticks.round() as i64,
+            rate,
+        }
+    }
+
+    pub fn seconds(self) -> f64 {
+        (self.value as f64 * self.rate.den as f64) / self.rate.num as f64
+    }
+
+    /// The same time at another rate, rounded to the nearest tick. Exact when it
+    /// falls on a tick, e.g. frames of 24000/1001 in a 1/24000 stream timebase.
+    pub fn rescale(self, rate: Rational) -> Self {
+        self.rescale_with(rate, Rounding::Nearest)
+    }
+
+    /// Like `rescale`, rounding down: the frame that is showing at this time.
+    pub fn floor_to(self, rate: Rational) -> Self {
+        self.rescale_with(rate, Rounding::Down)
+    }
+
+    /// Like `rescale`, rounding up: the first tick at or after this time.
+    pub fn ceil_to(self, rate: Rational) -> Self {
+        self.rescale_with(rate, Rounding::Up)
+    }
+
+    fn rescale_with(self, rate: Rational, rounding: Rounding) -> Self {
+        if rate == self.rate {
+            return self;
+        }
+
+        // value / self.rate * rate, in i128 to avoid overflow
+        let num = self.value as i128 * self.rate.den as i128 * rate.num as i128;
+        let den = self.rate.num as i128 * rate.den as i128;
+        let value = match rounding {
+            Rounding::Down => num.div_euclid(den),
+            Rounding::Up => -(-num).div_euclid(den),
+            Rounding::Nearest => (2 * num + den).div_euclid(2 * den),
+        };
+        Self {
+            value: value as i64,
+            rate,
+        }
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.value == 0
+    }
+
+    pub fn abs(self) -> Time {
+        if self.value < 0 {
+            -self
+        } else {
+            self
+        }
+    }
+
+    // Cross-multiplied so different rates compare exactly
+    fn cmp_key(self, other: Time) -> (i128, i128) {
+        (
+            self.value as i128 * self.rate.den as i128 * other.rate.num as i128,
+            other.value as i128 * other.rate.den as i128 * self.rate.num as i128,
+        )
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Rounding {
+    Down,
+    Up,
+    Nearest,
+}
+
+impl PartialEq for Time {
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b) = self.cmp_key(*other);
+        a == b
+    }
+}
+
+impl Eq for Time {}
+
+impl PartialOrd for Time {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Time {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (a, b) = self.cmp_key(*other);
+        a.cmp(&b)
+    }
+}
+
+// Arithmetic keeps the left-hand rate; the right side is rescaled to it, rounding
+// to the nearest tick if it differs. So `frame + 1µs` is still `frame` at a frame
+// rate: conform both sides first, or compare (which is exact across rates) instead.
+impl Add for Time {
+    type Output = Time;
+
+    fn add(self, rhs: Time) -> Time {
+        Time {
+            value: self.value + rhs.rescale(self.rate).value,
+            rate: self.rate,
+        }
+    }
+}
+
+impl Sub for Time {
+    type Output = Time;
+
+    fn sub(self, rhs: Time) -> Time {
+        Time {
+            value: self.value - rhs.rescale(self.rate).value,
+            rate: self.rate,
+        }
+    }
+}
+
+impl AddAssign for Time {
+    fn add_assign(&mut self, rhs: Time) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Time {
+    fn sub_assign(&mut self, rhs: Time) {
+        *self = *self - rhs;
+    }
+}
+
+impl Neg for Time {
+    type Output = Time;
+
+    fn neg(self) -> Time {
+        Time {
+            value: -self.value,
+            rate: self.rate,
+        }
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.3}s", self.seconds())
+    }
+}
+
+impl Serialize for Time {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.seconds())
+    }
+}
+
+impl<'de> Deserialize<'de> for Time {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let seconds = f64::deserialize(deserializer)?;
+        if !seconds.is_finite() {
+            return Err(serde::de::Error::custom(
+                "Time must be a finite number of seconds",
+            ));
+        }
+        Ok(Time::from_seconds(seconds, MICROSECONDS))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NTSC_FILM: Rational = Rational {
+        num: 24000,
+        den: 1001,
+    };
+
+    #[test]
+    fn test_rational_parsing() {
+        assert_eq!("24000/1001".parse::<Rational>().unwrap(), NTSC_FILM);
+        assert_eq!("23.976".parse::<Rational>().unwrap(), NTSC_FILM);
+        assert_eq!(Rational::from_f64(29.97), Rational::new(30000, 1001));
+        assert_eq!(Rational::from_f64(25.0), Rational::new(25, 1));
+        assert_eq!(Rational::new(50, 2), Rational::new(25, 1));
+        assert!("0/1".parse::<Rational>().is_err());
+        assert_eq!(NTSC_FILM.to_string(), "24000/1001");
+    }
+
+    #[test]
+    fn test_ntsc_frames_do_not_drift() {
+        // One hour of 23.976 frames, one frame at a time
+        let frame = Time::from_frames(1, NTSC_FILM);
+        let mut time = Time::zero(NTSC_FILM);
+        for _ in 0..86_314 {
+            time += frame;
+        }
+        assert_eq!(time, Time::from_frames(86_314, NTSC_FILM));
+
+        // Rescaled into a 1/24000 stream timebase (1001 ticks per frame) and back, exactly
+        let stream = time.rescale(Rational::new(24_000, 1));
+        assert_eq!(stream.value, 86_314 * 1001);
+        assert_eq!(stream.rescale(NTSC_FILM).value, 86_314);
+    }
+
+    #[test]
+    fn test_conform_and_compare() {
+        let rate = Rational::new(30000, 1001);
+        let seconds: Time = serde_json::from_str("1.001").unwrap();
+        assert_eq!(seconds.rescale(rate), Time::from_frames(30, rate));
+        assert_eq!(
+            Time::from_frames(45, rate)
+                .floor_to(Rational::new(1, 1))
+                .value,
+            1
+        );
+        assert_eq!(
+            Time::from_frames(1, rate)
+                .ceil_to(Rational::new(1, 1))
+                .value,
+            1
+        );
+
+        assert!(Time::from_frames(1, NTSC_FILM) > Time::from_frames(1, Rational::new(25, 1)));
+        assert_eq!(
+            Time::from_frames(1000, NTSC_FILM),
+            Time::from_frames(1_001_000, Rational::new(24_000, 1))
+        );
+        assert_eq!(
+            serde_json::to_string(&Time::from_frames(3, Rational::new(2, 1))).unwrap(),
+            "1.5"
+        );
+    }
+
+    #[test]
+    fn test_mixed_rate_arithmetic_rounds_to_left() {
+        let frame = Time::from_frames(1, Rational::new(25, 1));
+        let tick = Time::from_frames(1, MICROSECONDS);
+        assert_eq!(frame + tick, frame); // 1µs is no whole frame
+        assert_eq!((tick + frame).value, 40_001);
+        assert_eq!((frame - Time::from_frames(30_000, MICROSECONDS)).value, 0); // 0.75 frames round to one
+    }
+}
@@ -2,76 +2,230 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
 
+use super::framing::{ScaleMode, MAX_REFRAME};
+use super::history::{Edit, History};
+use super::keyframes::{self, ClipKeyframes, Keyframe};
+use super::placement::PlacementPolicy;
+use super::settings::ProjectSettings;
+use super::time::{Rational, Time};
+
 // --- Data Structures ---
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Placement of a clip on the canvas.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Transform {
+    pub position: [f32; 2],    // Offset from canvas center, in canvas widths/heights (+Y down)
+    pub scale: f32,            // 1.0 = the framed size (see `scale_mode`)
+    pub rotation: f32,         // Degrees, clockwise
+    pub opacity: f32,          // 0.0 - 1.0
+    pub scale_mode: ScaleMode, // How a frame of another shape fits the canvas
+    pub reframe: [f32; 2],     // Pan of the Fill crop, in source widths/heights (-0.5 - 0.5)
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            position: [0.0, 0.0],
+            scale: 1.0,
+            rotation: 0.0,
+            opacity: 1.0,
+            scale_mode: ScaleMode::default(),
+            reframe: [0.0, 0.0],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Clip {
     pub id: String,
     pub path: String,
     pub name: String,
-    pub start: f64,    // Global Timeline Time (Seconds)
-    pub duration: f64, // Length in seconds
-    pub offset: f64,   // Offset into the source file
+    pub start: Time,    // Global Timeline Time (frames at the timeline rate)
+    pub duration: Time, // Length
+    pub offset: Time,   // Offset into the source file
     pub track_id: i32,
     pub z_index: i32, // Rendering order (higher = on top)
+    #[serde(default)]
+    pub transform: Transform,
+    #[serde(default)]
+    pub keyframes: ClipKeyframes, // Animated properties (overrides `transform`)
+    #[serde(default)]
+    pub source_duration: Option<Time>, // Length of the source media, if known
+    #[serde(default)]
+    pub connected_to: Option<String>, // Anchor clip ID on another track; moves with it
 }
 
 impl Clip {
-    pub fn is_active(&self, time: f64) -> bool {
-        time >= self.start && time < (self.start + self.duration)
+    pub fn is_active(&self, time: Time) -> bool {
+        time >= self.start && time < self.end()
+    }
+
+    pub fn end(&self) -> Time {
+        self.start + self.duration
+    }
+
+    /// Clips touching end-to-start don't overlap.
+    pub fn overlaps(&self, start: Time, end: Time) -> bool {
+        self.start < end && self.end() > start
+    }
+
+    /// Rounds start, end, offset and keyframes to whole frames at `rate`.
+    /// The end is rounded on its own so touching clips still touch afterwards.
+    pub fn conform(&mut self, rate: Rational) {
+        let end = self.end().rescale(rate);
+        self.start = self.start.rescale(rate);
+        self.duration = end - self.start;
+        self.offset = self.offset.rescale(rate);
+        for track in self.keyframes.values_mut() {
+            track.conform(rate);
+        }
+    }
+
+    /// Cuts the clip at a global time. `self` keeps the left part; returns the right part.
+    pub fn split_off(&mut self, time: Time) -> Clip {
+        let mut right = self.clone();
+        right.id = Uuid::new_v4().to_string();
+        right.start = time;
+        right.offset = self.offset + (time - self.start);
+        right.duration = self.end() - time;
+        self.duration = time - self.start;
+        right
     }
 
     /// Converts global timeline time to local media time
-    pub fn get_media_time(&self, global_time: f64) -> f64 {
-        (global_time - self.start) + self.offset
+    pub fn get_media_time(&self, global_time: Time) -> Time {
+        self.offset + (global_time - self.start)
+    }
+
+    /// Value of an animated property at a global time, if it has keyframes.
+    pub fn value_at(&self, property: &str, global_time: Time) -> Option<f32> {
+        self.keyframes
+            .get(property)?
+            .evaluate(self.get_media_time(global_time))
+    }
+
+    /// The clip's transform at a global time, with keyframed properties applied.
+    pub fn transform_at(&self, global_time: Time) -> Transform {
+        let value = |property: &str, base: f32| self.value_at(property, global_time).unwrap_or(base);
+        let base = self.transform;
+
+        Transform {
+            position: [
+                value(keyframes::POSITION_X, base.position[0]),
+                value(keyframes::POSITION_Y, base.position[1]),
+            ],
+            scale: value(keyframes::SCALE, base.scale),
+            rotation: value(keyframes::ROTATION, base.rotation),
+            opacity: value(keyframes::OPACITY, base.opacity),
+            ..base
+        }
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// What a track holds. Decides which tools and renderers apply to its clips.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrackKind {
+    #[default]
+    Video,
+    Audio,
+    Text,
+    Adjustment, // Effects applied to everything below
+}
+
+impl TrackKind {
+    /// Whether the compositor draws clips on this kind of track.
+    pub fn has_video(self) -> bool {
+        self == TrackKind::Video
+    }
+
+    /// Whether the mixer plays clips on this kind of track (video clips keep their sound).
+    pub fn has_audio(self) -> bool {
+        matches!(self, TrackKind::Video | TrackKind::Audio)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Track {
     pub id: i32,
     pub name: String,
     pub is_muted: bool,
-    pub is_locked: bool,
+    pub is_locked: bool, // Clips on locked tracks can't be edited
+    #[serde(default)]
+    pub is_solo: bool, // If any track is soloed, only soloed tracks play
+    #[serde(default)]
+    pub kind: TrackKind,
+    #[serde(default)]
+    pub order: i32, // Stacking position (higher = on top)
+    #[serde(default)]
+    pub is_magnetic: bool, // Keeps clips gapless from 0s (see magnetic.rs)
+}
+
+impl Track {
+    pub fn new(id: i32, name: &str, kind: TrackKind, order: i32) -> Self {
+        Self {
+            id,
+            name: name.to_string(),
+            is_muted: false,
+            is_locked: false,
+            is_solo: false,
+            kind,
+            order,
+            is_magnetic: false,
+        }
+    }
+}
+
+/// A named point on the timeline (chapter, beat, note).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Marker {
+    pub id: String,
+    pub time: Time, // Global Timeline Time
+    pub name: String,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Timeline {
     pub tracks: HashMap<i32, Track>,
     pub clips: HashMap<String, Clip>, // Indexed by UUID
-    pub duration: f64,
+    #[serde(default)]
+    pub markers: Vec<Marker>, // Sorted by time
+    pub duration: Time,
+    #[serde(default)]
+    pub settings: ProjectSettings, // Canvas, frame rate, background (see settings.rs)
 }
 
 impl Timeline {
     pub fn new() -> Self {
         let mut tracks = HashMap::new();
         // Create default tracks
-        tracks.insert(
-            1,
-            Track {
-                id: 1,
-                name: "Video 1".into(),
-                is_muted: false,
-                is_locked: false,
-            },
-        );
-        tracks.insert(
-            2,
-            Track {
-                id: 2,
-                name: "Video 2".into(),
-                is_muted: false,
-                is_locked: false,
-            },
-        );
+        tracks.insert(1, Track::new(1, "Video 1", TrackKind::Video, 1));
+        tracks.insert(2, Track::new(2, "Video 2", TrackKind::Video, 2));
 
         Self {
             tracks,
             clips: HashMap::new(),
-            duration: 0.0,
+            markers: Vec::new(),
+            duration: Time::default(),
+            settings: ProjectSettings::default(),
+        }
+    }
+
+    /// Rounds every clip and marker to whole frames at the timeline rate.
+    /// Times arriving from JSON are in float seconds and may fall between frames.
+    pub fn conform(&mut self) {
+        let rate = self.settings.frame_rate;
+        for clip in self.clips.values_mut() {
+            clip.conform(rate);
+        }
+        for marker in &mut self.markers {
+            marker.time = marker.time.rescale(rate);
         }
+        self.duration = self.duration.rescale(rate);
     }
 }
 
@@ -79,63 +233,565 @@ impl Timeline {
 
 pub struct TimelineManager {
     pub timeline: Timeline,
+    history: History, // Every edit below goes through `commit` and can be undone
 }
 
 impl TimelineManager {
     pub fn new() -> Self {
         Self {
             timeline: Timeline::new(),
+            history: History::new(),
         }
     }
 
-    pub fn add_clip(&mut self, mut clip: Clip) {
-        // Ensure ID
-        if clip.id.is_empty() {
-            clip.id = Uuid::new_v4().to_string();
+    /// Wraps an existing timeline (e.g. a snapshot for export), with empty history.
+    pub fn from_timeline(mut timeline: Timeline) -> Self {
+        timeline.conform();
+        Self {
+            timeline,
+            history: History::new(),
         }
+    }
 
-        // Auto-assign Z-Index based on Track ID if not set
-        if clip.z_index == 0 {
-            clip.z_index = clip.track_id;
+    pub fn frame_rate(&self) -> Rational {
+        self.timeline.settings.frame_rate
+    }
+
+    /// Nearest frame to a time in seconds (as sent by the frontend).
+    pub fn frame_time(&self, seconds: f64) -> Time {
+        Time::from_seconds(seconds, self.frame_rate())
+    }
+
+    /// Nearest frame to `time`, which may be at any rate.
+    pub fn conform_time(&self, time: Time) -> Time {
+        time.rescale(self.frame_rate())
+    }
+
+    /// Adds a clip, failing if it overlaps another clip on its track.
+    /// See `place_clip` for the other placement policies.
+    pub fn add_clip(&mut self, clip: Clip) -> Result<(), String> {
+        self.place_clip(clip, PlacementPolicy::Reject)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Replaces an existing clip (move, trim, transform...).
+    pub fn update_clip(&mut self, mut clip: Clip) -> Result<(), String> {
+        clip.conform(self.frame_rate());
+        let before = self
+            .timeline
+            .clips
+            .get(&clip.id)
+            .cloned()
+            .ok_or(format!("Clip not found: {}", clip.id))?;
+        self.check_unlocked(before.track_id)?;
+        self.check_unlocked(clip.track_id)?; // Moving onto a locked track
+
+        let edit = Edit::clip(clip.id.clone(), Some(before), Some(clip));
+        self.commit("Update Clip", vec![edit]);
+        Ok(())
+    }
+
+    /// Replaces all clips at once (bulk sync from the frontend), as one undo step.
+    /// Only changed clips are recorded. Fails if a change touches a locked track.
+    pub fn replace_clips(&mut self, clips: Vec<Clip>) -> Result<(), String> {
+        let mut incoming: HashMap<String, Clip> = HashMap::new();
+        for mut clip in clips {
+            if clip.id.is_empty() {
+                clip.id = Uuid::new_v4().to_string();
+            }
+            if clip.z_index == 0 {
+                clip.z_index = clip.track_id;
+            }
+            clip.conform(self.frame_rate());
+            incoming.insert(clip.id.clone(), clip);
         }
 
-        self.timeline.clips.insert(clip.id.clone(), clip);
+        let mut edits = Vec::new();
+        for (id, before) in &self.timeline.clips {
+            let after = incoming.get(id);
+            if after == Some(before) {
+                continue;
+            }
+            self.check_unlocked(before.track_id)?;
+            if let Some(after) = after {
+                self.check_unlocked(after.track_id)?; // Moving onto a locked track
+            }
+            edits.push(Edit::clip(id.clone(), Some(before.clone()), after.cloned()));
+        }
+        for (id, clip) in incoming {
+            if self.timeline.clips.contains_key(&id) {
+                continue; // Handled above
+            }
+            self.check_unlocked(clip.track_id)?;
+            edits.push(Edit::clip(id, None, Some(clip)));
+        }
+
+        self.commit("Update Composition", edits);
+        Ok(())
+    }
+
+    /// Replaces the whole timeline (project open). Clears undo history.
+    pub fn load_timeline(&mut self, mut timeline: Timeline) {
+        timeline.conform();
+        self.timeline = timeline;
+        self.history.clear();
         self.recalculate_duration();
     }
 
-    pub fn remove_clip(&mut self, clip_id: &str) {
-        self.timeline.clips.remove(clip_id);
+    pub fn remove_clip(&mut self, clip_id: &str) -> Result<(), String> {
+        let Some(before) = self.timeline.clips.get(clip_id).cloned() else {
+            return Ok(());
+        };
+        self.check_unlocked(before.track_id)?;
+
+        let edit = Edit::clip(clip_id.to_string(), Some(before), None);
+        self.commit("Remove Clip", vec![edit]);
+        Ok(())
+    }
+
+    /// Adds or replaces the keyframe at `keyframe.time` (nearest frame) on a clip property.
+    pub fn set_keyframe(&mut self, clip_id: &str, property: &str, mut keyframe: Keyframe) -> Result<(), String> {
+        keyframe.time = self.conform_time(keyframe.time);
+        let mut clip = self
+            .timeline
+            .clips
+            .get(clip_id)
+            .cloned()
+            .ok_or(format!("Clip not found: {}", clip_id))?;
+        self.check_unlocked(clip.track_id)?;
+        clip.keyframes
+            .entry(property.to_string())
+            .or_default()
+            .insert(keyframe);
+
+        self.commit_clip("Set Keyframe", clip);
+        Ok(())
+    }
+
+    /// Removes the keyframe on the frame nearest `time`.
+    pub fn remove_keyframe(&mut self, clip_id: &str, property: &str, time: Time) -> Result<(), String> {
+        let time = self.conform_time(time);
+        let mut clip = self
+            .timeline
+            .clips
+            .get(clip_id)
+            .cloned()
+            .ok_or(format!("Clip not found: {}", clip_id))?;
+        self.check_unlocked(clip.track_id)?;
+        let track = clip
+            .keyframes
+            .get_mut(property)
+            .ok_or(format!("Property has no keyframes: {}", property))?;
+
+        if !track.remove(time) {
+            return Err(format!("No keyframe at {} on {}", time, property));
+        }
+        if track.is_empty() {
+            clip.keyframes.remove(property);
+        }
+
+        self.commit_clip("Remove Keyframe", clip);
+        Ok(())
+    }
+
+    /// Sets how a clip is fitted to the canvas and where a Fill crop is panned.
+    pub fn set_clip_framing(&mut self, clip_id: &str, scale_mode: ScaleMode, reframe: [f32; 2]) -> Result<(), String> {
+        if !reframe.iter().all(|pan| (-MAX_REFRAME..=MAX_REFRAME).contains(pan)) {
+            return Err(format!("Reframe must be between -{0} and {0}", MAX_REFRAME));
+        }
+        let mut clip = self
+            .timeline
+            .clips
+            .get(clip_id)
+            .cloned()
+            .ok_or(format!("Clip not found: {}", clip_id))?;
+        self.check_unlocked(clip.track_id)?;
+        clip.transform.scale_mode = scale_mode;
+        clip.transform.reframe = reframe;
+
+        self.commit_clip("Reframe Clip", clip);
+        Ok(())
+    }
+
+    // --- Tracks ---
+
+    /// Adds a track on top of the stack and returns it.
+    pub fn add_track(&mut self, name: &str, kind: TrackKind) -> Track {
+        let tracks = &self.timeline.tracks;
+        let id = tracks.keys().max().map_or(1, |id| id + 1);
+        let order = tracks.values().map(|t| t.order).max().map_or(1, |order| order + 1);
+
+        let track = Track::new(id, name, kind, order);
+        let edit = Edit::Track {
+            id,
+            before: None,
+            after: Some(track.clone()),
+        };
+        self.commit("Add Track", vec![edit]);
+        track
+    }
+
+    /// Removes a track together with its clips.
+    pub fn remove_track(&mut self, track_id: i32) -> Result<(), String> {
+        let track = self.get_track(track_id)?.clone();
+        self.check_unlocked(track_id)?;
+
+        let mut edits: Vec<Edit> = self
+            .timeline
+            .clips
+            .values()
+            .filter(|clip| clip.track_id == track_id)
+            .map(|clip| Edit::clip(clip.id.clone(), Some(clip.clone()), None))
+            .collect();
+        edits.push(Edit::Track {
+            id: track_id,
+            before: Some(track),
+            after: None,
+        });
+
+        self.commit("Remove Track", edits);
+        Ok(())
+    }
+
+    pub fn rename_track(&mut self, track_id: i32, name: &str) -> Result<(), String> {
+        self.edit_track(track_id, "Rename Track", |track| track.name = name.to_string())
+    }
+
+    /// Restacks tracks. `track_ids` lists every track, bottom to top.
+    pub fn reorder_tracks(&mut self, track_ids: &[i32]) -> Result<(), String> {
+        let mut sorted = track_ids.to_vec();
+        sorted.sort_unstable();
+        sorted.dedup();
+        if sorted.len() != track_ids.len() || sorted.len() != self.timeline.tracks.len() {
+            return Err("Track order must list every track exactly once".to_string());
+        }
+
+        let mut edits = Vec::new();
+        for (index, id) in track_ids.iter().enumerate() {
+            let before = self.get_track(*id)?;
+            let order = index as i32 + 1;
+            if before.order != order {
+                edits.push(Edit::Track {
+                    id: *id,
+                    before: Some(before.clone()),
+                    after: Some(Track {
+                        order,
+                        ..before.clone()
+                    }),
+                });
+            }
+        }
+
+        self.commit("Reorder Tracks", edits);
+        Ok(())
+    }
+
+    pub fn set_track_locked(&mut self, track_id: i32, locked: bool) -> Result<(), String> {
+        let label = if locked { "Lock Track" } else { "Unlock Track" };
+        self.edit_track(track_id, label, |track| track.is_locked = locked)
+    }
+
+    pub fn set_track_muted(&mut self, track_id: i32, muted: bool) -> Result<(), String> {
+        let label = if muted { "Mute Track" } else { "Unmute Track" };
+        self.edit_track(track_id, label, |track| track.is_muted = muted)
+    }
+
+    pub fn set_track_solo(&mut self, track_id: i32, solo: bool) -> Result<(), String> {
+        let label = if solo { "Solo Track" } else { "Unsolo Track" };
+        self.edit_track(track_id, label, |track| track.is_solo = solo)
+    }
+
+    /// Magnetic tracks keep their clips back to back; enabling closes existing gaps.
+    pub fn set_track_magnetic(&mut self, track_id: i32, magnetic: bool) -> Result<(), String> {
+        self.check_unlocked(track_id)?;
+        let label = if magnetic { "Enable Magnetic Track" } else { "Disable Magnetic Track" };
+        self.edit_track(track_id, label, |track| track.is_magnetic = magnetic)
+    }
+
+    fn get_track(&self, track_id: i32) -> Result<&Track, String> {
+        self.timeline
+            .tracks
+            .get(&track_id)
+            .ok_or(format!("Track not found: {}", track_id))
+    }
+
+    // Rejects edits to clips on a locked track. Unknown tracks are not locked.
+    pub(super) fn check_unlocked(&self, track_id: i32) -> Result<(), String> {
+        match self.timeline.tracks.get(&track_id) {
+            Some(track) if track.is_locked => Err(format!("Track \"{}\" is locked", track.name)),
+            _ => Ok(()),
+        }
+    }
+
+    fn edit_track(&mut self, track_id: i32, label: &str, change: impl FnOnce(&mut Track)) -> Result<(), String> {
+        let before = self.get_track(track_id)?.clone();
+        let mut after = before.clone();
+        change(&mut after);
+        if after == before {
+            return Ok(());
+        }
+
+        let edit = Edit::Track {
+            id: track_id,
+            before: Some(before),
+            after: Some(after),
+        };
+        self.commit(label, vec![edit]);
+        Ok(())
+    }
+
+    // --- Markers ---
+
+    /// Adds a marker and returns its ID.
+    pub fn add_marker(&mut self, time: Time, name: &str) -> Result<String, String> {
+        let time = self.conform_time(time);
+        if time.value < 0 {
+            return Err(format!("Invalid marker time: {}", time));
+        }
+
+        let marker = Marker {
+            id: Uuid::new_v4().to_string(),
+            time,
+            name: name.to_string(),
+        };
+        let id = marker.id.clone();
+        let edit = Edit::Marker {
+            id: id.clone(),
+            before: None,
+            after: Some(marker),
+        };
+        self.commit("Add Marker", vec![edit]);
+        Ok(id)
+    }
+
+    pub fn remove_marker(&mut self, marker_id: &str) -> Result<(), String> {
+        let before = self
+            .timeline
+            .markers
+            .iter()
+            .find(|m| m.id == marker_id)
+            .cloned()
+            .ok_or(format!("Marker not found: {}", marker_id))?;
+
+        let edit = Edit::Marker {
+            id: marker_id.to_string(),
+            before: Some(before),
+            after: None,
+        };
+        self.commit("Remove Marker", vec![edit]);
+        Ok(())
+    }
+
+    // --- History ---
+
+    /// Starts an undo group: edits until `end_edit_group` undo as one step (e.g. a drag).
+    pub fn begin_edit_group(&mut self, label: &str) {
+        self.history.begin_group(label);
+    }
+
+    pub fn end_edit_group(&mut self) {
+        self.history.end_group();
+    }
+
+    /// Returns the label of the undone step, or None if there was nothing to undo.
+    pub fn undo(&mut self) -> Option<String> {
+        let label = self.history.undo(&mut self.timeline)?;
+        self.recalculate_duration();
+        Some(label)
+    }
+
+    pub fn redo(&mut self) -> Option<String> {
+        let label = self.history.redo(&mut self.timeline)?;
+        self.recalculate_duration();
+        Some(label)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    // Applies edits and records them as one undo step
+    // Magnetic tracks and connected clips are settled here, in the same step
+    pub(super) fn commit(&mut self, label: &str, mut edits: Vec<Edit>) {
+        let starts: HashMap<String, Time> = self
+            .timeline
+            .clips
+            .values()
+            .map(|clip| (clip.id.clone(), clip.start))
+            .collect();
+
+        for edit in &edits {
+            edit.apply(&mut self.timeline);
+        }
+
+        let follow_up = self.magnetic_edits(&starts, &edits);
+        for edit in &follow_up {
+            edit.apply(&mut self.timeline);
+        }
+        edits.extend(follow_up);
+
+        self.history.record(label, edits);
         self.recalculate_duration();
     }
 
-    pub fn get_active_clips(&self, time: f64) -> Vec<&Clip> {
+    // Shorthand for replacing an existing clip with an edited copy
+    fn commit_clip(&mut self, label: &str, clip: Clip) {
+        let before = self.timeline.clips.get(&clip.id).cloned();
+        let edit = Edit::clip(clip.id.clone(), before, Some(clip));
+        self.commit(label, vec![edit]);
+    }
+
+    pub fn get_active_clips(&self, time: Time) -> Vec<&Clip> {
+        self.playing_clips(|_| true, |c| c.is_active(time))
+    }
+
+    /// Clips overlapping `[start, end)` on playing tracks, in the same order as `get_active_clips`.
+    pub fn get_clips_in_range(&self, start: Time, end: Time) -> Vec<&Clip> {
+        self.playing_clips(|_| true, |c| c.overlaps(start, end))
+    }
+
+    /// `get_active_clips` on the tracks the compositor draws.
+    pub fn get_video_clips(&self, time: Time) -> Vec<&Clip> {
+        self.playing_clips(TrackKind::has_video, |c| c.is_active(time))
+    }
+
+    /// `get_clips_in_range` on the tracks the compositor draws.
+    pub fn get_video_clips_in_range(&self, start: Time, end: Time) -> Vec<&Clip> {
+        self.playing_clips(TrackKind::has_video, |c| c.overlaps(start, end))
+    }
+
+    /// `get_clips_in_range` on the tracks the mixer plays.
+    pub fn get_audio_clips_in_range(&self, start: Time, end: Time) -> Vec<&Clip> {
+        self.playing_clips(TrackKind::has_audio, |c| c.overlaps(start, end))
+    }
+
+    // Clips matching `include` on tracks of a `kind` that aren't muted (or soloed away),
+    // bottom to top. Clips on a missing track count as video
+    fn playing_clips(&self, kind: impl Fn(TrackKind) -> bool, include: impl Fn(&Clip) -> bool) -> Vec<&Clip> {
+        let tracks = &self.timeline.tracks;
+        let any_solo = tracks.values().any(|t| t.is_solo);
+
         let mut active: Vec<&Clip> = self
             .timeline
             .clips
             .values()
-            .filter(|c| c.is_active(time))
+            .filter(|c| include(c))
             .filter(|c| {
-                // Check if track is muted
-                self.timeline
-                    .tracks
+                // Check if track is muted (or another track is soloed)
+                tracks
                     .get(&c.track_id)
-                    .map(|t| !t.is_muted)
-                    .unwrap_or(true) // Default to visible if track missing
+                    .map(|t| kind(t.kind) && !t.is_muted && (t.is_solo || !any_solo))
+                    .unwrap_or(kind(TrackKind::default()) && !any_solo) // Default to visible if track missing
             })
             .collect();
 
-        // Sort by Z-Index (Painter's Algorithm)
-        active.sort_by_key(|c| c.z_index);
+        // Sort by track stacking order, then Z-Index (Painter's Algorithm)
+        active.sort_by_key(|c| {
+            let order = tracks.get(&c.track_id).map_or(0, |t| t.order);
+            (order, c.z_index)
+        });
         active
     }
 
     fn recalculate_duration(&mut self) {
+        let zero = Time::zero(self.frame_rate());
         let max_end = self
             .timeline
             .clips
             .values()
-            .map(|c| c.start + c.duration)
-            .fold(0.0_f64, f64::max);
+            .map(Clip::end)
+            .fold(zero, Time::max);
         self.timeline.duration = max_end;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::time::DEFAULT_FRAME_RATE;
+
+    fn t(seconds: f64) -> Time {
+        Time::from_seconds(seconds, DEFAULT_FRAME_RATE)
+    }
+
+    fn clip(id: &str, track_id: i32) -> Clip {
+        Clip {
+            id: id.into(),
+            start: t(0.0),
+            duration: t(5.0),
+            track_id,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_locked_track_rejects_edits() {
+        let mut manager = TimelineManager::new();
+        manager.add_clip(clip("a", 1)).unwrap();
+        manager.set_track_locked(1, true).unwrap();
+
+        assert!(manager.add_clip(clip("b", 1)).is_err());
+        assert!(manager.remove_clip("a").is_err());
+        assert!(manager.update_clip(clip("a", 2)).is_err());
+        assert!(manager.remove_track(1).is_err());
+        assert!(manager.update_clip(Clip { start: t(1.0), ..clip("a", 1) }).is_err());
+
+        // Moving a clip onto a locked track
+        manager.add_clip(clip("c", 2)).unwrap();
+        assert!(manager.update_clip(clip("c", 1)).is_err());
+        assert!(manager.replace_clips(vec![clip("c", 2)]).is_err()); // Drops "a"
+        assert!(manager.replace_clips(vec![clip("a", 1)]).is_ok()); // "a" untouched
+
+        manager.set_track_locked(1, false).unwrap();
+        assert!(manager.remove_clip("a").is_ok());
+    }
+
+    #[test]
+    fn test_solo_and_reorder() {
+        let mut manager = TimelineManager::new();
+        let overlay = manager.add_track("Titles", TrackKind::Text);
+        assert_eq!(overlay.id, 3);
+
+        manager.add_clip(clip("a", 1)).unwrap();
+        manager.add_clip(clip("b", 2)).unwrap();
+        manager.add_clip(clip("c", overlay.id)).unwrap();
+
+        let ids = |clips: Vec<&Clip>| clips.iter().map(|c| c.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(manager.get_active_clips(t(1.0))), ["a", "b", "c"]);
+
+        manager.reorder_tracks(&[3, 1, 2]).unwrap();
+        assert_eq!(ids(manager.get_active_clips(t(1.0))), ["c", "a", "b"]);
+        assert!(manager.reorder_tracks(&[1, 2]).is_err());
+
+        manager.set_track_solo(2, true).unwrap();
+        assert_eq!(ids(manager.get_active_clips(t(1.0))), ["b"]);
+        assert_eq!(ids(manager.get_clips_in_range(t(4.0), t(6.0))), ["b"]);
+        assert!(manager.get_clips_in_range(t(5.0), t(6.0)).is_empty()); // Ends are exclusive
+
+        manager.remove_track(2).unwrap();
+        assert!(!manager.timeline.clips.contains_key("b"));
+        manager.undo();
+        assert!(manager.timeline.clips.contains_key("b"));
+    }
+
+    #[test]
+    fn test_track_kind_picks_renderer() {
+        let mut manager = TimelineManager::new();
+        let music = manager.add_track("Music", TrackKind::Audio);
+        let titles = manager.add_track("Titles", TrackKind::Text);
+        manager.add_clip(clip("picture", 1)).unwrap();
+        manager.add_clip(clip("music", music.id)).unwrap();
+        manager.add_clip(clip("title", titles.id)).unwrap();
+
+        let ids = |clips: Vec<&Clip>| clips.iter().map(|c| c.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(manager.get_video_clips(t(1.0))), ["picture"]);
+        assert_eq!(ids(manager.get_video_clips_in_range(t(0.0), t(1.0))), ["picture"]);
+        assert_eq!(ids(manager.get_audio_clips_in_range(t(0.0), t(1.0))), ["picture", "music"]);
+        assert_eq!(manager.get_active_clips(t(1.0)).len(), 3);
+    }
+}
@@ -0,0 +1,240 @@
+use super::frame_pool::{FramePool, PooledBuffer};
+
+/// How a decoded frame's planes are laid out. YUV layouts are converted to RGB in the shader.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlaneLayout {
+    /// One plane of packed RGBA (non-YUV sources, scaled output)
+    Rgba,
+    /// Y, then U and V at half width and height (yuv420p, yuvj420p)
+    I420,
+    /// Y, then interleaved UV at half width and height (nv12, hardware decoders)
+    Nv12,
+}
+
+/// Y'CbCr -> R'G'B' matrix, from the stream's color space.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum YuvMatrix {
+    Bt601,
+    Bt709,
+    Bt2020,
+}
+
+impl YuvMatrix {
+    // Luma weights of red and blue (Kr, Kb)
+    fn weights(&self) -> (f32, f32) {
+        match self {
+            YuvMatrix::Bt601 => (0.299, 0.114),
+            YuvMatrix::Bt709 => (0.2126, 0.0722),
+            YuvMatrix::Bt2020 => (0.2627, 0.0593),
+        }
+    }
+}
+
+/// Color properties a YUV frame is converted with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct YuvColor {
+    pub matrix: YuvMatrix,
+    pub full_range: bool, // 0-255 (JPEG) instead of 16-235 luma / 16-240 chroma (MPEG)
+}
+
+impl YuvColor {
+    /// Rows of R, G and B as weights of (Y, U, V, 1), all in 0.0 - 1.0 texture values.
+    /// Range expansion is folded in, so the shader does one dot product per channel.
+    pub fn conversion(&self) -> [[f32; 4]; 3] {
+        let (kr, kb) = self.matrix.weights();
+        let kg = 1.0 - kr - kb;
+
+        // y' = y * y_scale + y_offset, c' = c * c_scale + c_offset (c' in -0.5 - 0.5)
+        let (y_scale, y_offset, c_scale, c_offset) = if self.full_range {
+            (1.0, 0.0, 1.0, -128.0 / 255.0)
+        } else {
+            (255.0 / 219.0, -16.0 / 219.0, 255.0 / 224.0, -128.0 / 224.0)
+        };
+
+        // R = Y + cr_r * V, G = Y + cb_g * U + cr_g * V, B = Y + cb_b * U
+        let cr_r = 2.0 * (1.0 - kr);
+        let cb_g = -2.0 * kb * (1.0 - kb) / kg;
+        let cr_g = -2.0 * kr * (1.0 - kr) / kg;
+        let cb_b = 2.0 * (1.0 - kb);
+
+        let row = |cb: f32, cr: f32| {
+            [
+                y_scale,
+                cb * c_scale,
+                cr * c_scale,
+                y_offset + (cb + cr) * c_offset,
+            ]
+        };
+        [row(0.0, cr_r), row(cb_g, cr_g), row(cb_b, 0.0)]
+    }
+}
+
+/// One plane's rows, `stride` bytes apart (row padding included, as FFmpeg decoded it).
+#[derive(Debug)]
+pub struct Plane {
+    pub data: PooledBuffer,
+    pub stride: u32,
+}
+
+/// A decoded frame as it is cached and uploaded. Its plane buffers go back to the
+/// decoder's `FramePool` when the last reference is dropped.
+#[derive(Debug)]
+pub struct VideoFrame {
+    pub layout: PlaneLayout,
+    pub width: u32,
+    pub height: u32,
+    pub planes: Vec<Plane>,
+    pub color: YuvColor, // Unused for RGBA
+}
+
+impl VideoFrame {
+    /// A frame of tightly packed RGBA pixels.
+    pub fn rgba(width: u32, height: u32, pixels: PooledBuffer) -> Self {
+        Self {
+            layout: PlaneLayout::Rgba,
+            width,
+            height,
+            planes: vec![Plane {
+                data: pixels,
+                stride: width * 4,
+            }],
+            color: YuvColor {
+                matrix: YuvMatrix::Bt709,
+                full_range: true,
+            },
+        }
+    }
+
+    /// Copies the planes of an 8-bit 4:2:0 frame into buffers from `pool`, one copy
+    /// per plane with the stride kept. None for other formats, which are converted
+    /// to RGBA on the CPU instead.
+    pub fn from_yuv(frame: &ffmpeg::util::frame::Video, pool: &FramePool) -> Option<Self> {
+        use ffmpeg::format::Pixel;
+
+        let (layout, range_from_format) = match frame.format() {
+            Pixel::YUV420P => (PlaneLayout::I420, false),
+            Pixel::YUVJ420P => (PlaneLayout::I420, true),
+            Pixel::NV12 => (PlaneLayout::Nv12, false),
+            _ => return None,
+        };
+        let (width, height) = (frame.width(), frame.height());
+
+        let planes = plane_sizes(layout, width, height)
+            .into_iter()
+            .enumerate()
+            .map(|(index, (_, plane_height, _))| {
+                let stride = frame.stride(index);
+                Plane {
+                    data: pool.copy(&frame.data(index)[..stride * plane_height as usize]),
+                    stride: stride as u32,
+                }
+            })
+            .collect();
+
+        Some(Self {
+            layout,
+            width,
+            height,
+            planes,
+            color: frame_color(frame, range_from_format),
+        })
+    }
+
+    /// The pixels of an RGBA frame (always tightly packed).
+    pub fn rgba_pixels(&self) -> Option<&[u8]> {
+        match self.layout {
+            PlaneLayout::Rgba => self.planes.first().map(|plane| &plane.data[..]),
+            _ => None,
+        }
+    }
+
+    /// Bytes held, row padding included.
+    pub fn byte_size(&self) -> usize {
+        self.planes.iter().map(|plane| plane.data.len()).sum()
+    }
+}
+
+/// Width, height and bytes per pixel of each plane of a `width` x `height` frame.
+pub fn plane_sizes(layout: PlaneLayout, width: u32, height: u32) -> Vec<(u32, u32, u32)> {
+    let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+    match layout {
+        PlaneLayout::Rgba => vec![(width, height, 4)],
+        PlaneLayout::I420 => vec![
+            (width, height, 1),
+            (chroma_width, chroma_height, 1),
+            (chroma_width, chroma_height, 1),
+        ],
+        PlaneLayout::Nv12 => vec![(width, height, 1), (chroma_width, chroma_height, 2)],
+    }
+}
+
+// Unspecified properties follow FFmpeg's guess: BT.709 for HD, BT.601 below, limited range
+fn frame_color(frame: &ffmpeg::util::frame::Video, full_range: bool) -> YuvColor {
+    use ffmpeg::color::{Range, Space};
+
+    let matrix = match frame.color_space() {
+        Space::BT709 => YuvMatrix::Bt709,
+        Space::BT470BG | Space::SMPTE170M | Space::SMPTE240M | Space::FCC => YuvMatrix::Bt601,
+        Space::BT2020NCL | Space::BT2020CL => YuvMatrix::Bt2020,
+        _ if frame.height() >= 720 => YuvMatrix::Bt709,
+        _ => YuvMatrix::Bt601,
+    };
+    YuvColor {
+        matrix,
+        full_range: full_range || frame.color_range() == Range::JPEG,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 8-bit Y'CbCr -> 8-bit R'G'B' through the conversion rows
+    fn convert(color: YuvColor, yuv: [u8; 3]) -> [u8; 3] {
+        let [y, u, v] = yuv.map(|c| f32::from(c) / 255.0);
+        color.conversion().map(|row| {
+            let value = row[0] * y + row[1] * u + row[2] * v + row[3];
+            (value.clamp(0.0, 1.0) * 255.0).round() as u8
+        })
+    }
+
+    #[test]
+    fn test_limited_range_expands_to_full() {
+        for matrix in [YuvMatrix::Bt601, YuvMatrix::Bt709, YuvMatrix::Bt2020] {
+            let limited = YuvColor { matrix, full_range: false };
+            assert_eq!(convert(limited, [16, 128, 128]), [0, 0, 0]);
+            assert_eq!(convert(limited, [235, 128, 128]), [255, 255, 255]);
+
+            let full = YuvColor { matrix, full_range: true };
+            assert_eq!(convert(full, [0, 128, 128]), [0, 0, 0]);
+            assert_eq!(convert(full, [255, 128, 128]), [255, 255, 255]);
+        }
+    }
+
+    fn assert_close(actual: [u8; 3], expected: [u8; 3]) {
+        let delta = actual.iter().zip(expected).map(|(a, e)| a.abs_diff(e)).max().unwrap();
+        assert!(delta <= 1, "{:?}, expected {:?}", actual, expected); // 8-bit YUV rounding
+    }
+
+    #[test]
+    fn test_matrices_match_reference_colors() {
+        // 75% red in limited range, as the standards' color bar tables give it
+        let bt601 = YuvColor { matrix: YuvMatrix::Bt601, full_range: false };
+        let bt709 = YuvColor { matrix: YuvMatrix::Bt709, full_range: false };
+        assert_close(convert(bt601, [65, 100, 212]), [191, 0, 0]);
+        assert_close(convert(bt709, [51, 109, 212]), [191, 0, 0]);
+
+        // The wrong matrix visibly shifts the color
+        let shifted = convert(bt601, [51, 109, 212]);
+        assert!(shifted[0].abs_diff(191) > 10, "{:?}", shifted);
+    }
+
+    #[test]
+    fn test_chroma_planes_round_up() {
+        assert_eq!(plane_sizes(PlaneLayout::I420, 5, 3), [(5, 3, 1), (3, 2, 1), (3, 2, 1)]);
+        assert_eq!(plane_sizes(PlaneLayout::Nv12, 4, 2), [(4, 2, 1), (2, 1, 2)]);
+        let frame = VideoFrame::rgba(2, 2, vec![0; 16].into());
+        assert_eq!(frame.byte_size(), 16);
+        assert_eq!(frame.rgba_pixels().map(<[u8]>::len), Some(16));
+    }
+}
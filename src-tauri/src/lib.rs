@@ -71,14 +71,47 @@ pub fn run() {
             attach_wgpu_renderer,
             export_video,
             commands::library::load_file,
+            commands::project::save_project,
+            commands::project::open_project,
+            commands::project::get_project_settings,
+            commands::project::set_project_settings,
             commands::timeline::update_composition,
             commands::timeline::add_clip,
             commands::timeline::remove_clip,
+            commands::timeline::set_keyframe,
+            commands::timeline::remove_keyframe,
+            commands::timeline::set_clip_framing,
+            commands::timeline::update_clip,
+            commands::timeline::split_clip,
+            commands::timeline::trim_clip_in,
+            commands::timeline::trim_clip_out,
+            commands::timeline::ripple_delete,
+            commands::timeline::slip_clip,
+            commands::timeline::slide_clip,
+            commands::timeline::connect_clip,
+            commands::timeline::add_track,
+            commands::timeline::remove_track,
+            commands::timeline::rename_track,
+            commands::timeline::reorder_tracks,
+            commands::timeline::set_track_locked,
+            commands::timeline::set_track_muted,
+            commands::timeline::set_track_solo,
+            commands::timeline::set_track_magnetic,
+            commands::timeline::add_marker,
+            commands::timeline::remove_marker,
+            commands::timeline::snap_time,
+            commands::timeline::begin_edit_group,
+            commands::timeline::end_edit_group,
+            commands::timeline::undo,
+            commands::timeline::redo,
             play,
             pause,
             seek,
             seek,
             get_playback_state,
+            set_frame_cache_budget,
+            set_hardware_decoding,
+            get_decode_paths,
             update_viewport
         ])
         .run(tauri::generate_context!())
@@ -0,0 +1,16 @@
+// WebGPU Shader: Project Background
+// One triangle covering the viewport (the canvas rect). The color comes from the
+// pipeline's blend constant, so no bindings are needed
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    // (-1, -1), (3, -1), (-1, 3): the viewport is inside, the rest is clipped
+    let x = f32(in_vertex_index & 1u) * 4.0 - 1.0;
+    let y = f32(in_vertex_index >> 1u) * 4.0 - 1.0;
+    return vec4<f32>(x, y, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 1.0, 1.0, 1.0); // Scaled by the blend constant
+}
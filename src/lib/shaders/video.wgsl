@@ -1,25 +1,88 @@
 // WebGPU Shader: Video Processing
-// Handles YUV -> RGB conversion and aspect ratio correction
+// Draws one clip layer as a transformed quad (framing, position, scale, rotation, opacity)
+// and converts YUV planes to RGB (BT.601 / BT.709 / BT.2020, limited or full range)
 
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) uv: vec2<f32>,
 };
 
+// Must match TransformUniform in model.rs
+struct Transform {
+    position: vec2<f32>, // Canvas fractions from center, +Y down
+    scale: f32,
+    rotation: f32,       // Degrees, clockwise
+    opacity: f32,
+    aspect: f32,         // Canvas width / height
+    size: vec2<f32>,     // Framed quad size in canvas fractions (fit / fill / stretch / none)
+    uv_origin: vec2<f32>, // Sampled source window (fill crop + reframe pan)
+    uv_size: vec2<f32>,
+};
+
+@group(1) @binding(0) var<uniform> transform: Transform;
+
 @vertex
 fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    // Two triangles covering the canvas before transform
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(-1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+    );
+    let corner = corners[in_vertex_index];
+
+    // Scale and rotate in aspect-corrected space so right angles survive
+    var p = corner * transform.size * transform.scale;
+    p.x = p.x * transform.aspect;
+    let angle = -radians(transform.rotation); // Clip space is +Y up
+    let c = cos(angle);
+    let s = sin(angle);
+    p = vec2<f32>(p.x * c - p.y * s, p.x * s + p.y * c);
+    p.x = p.x / transform.aspect;
+
+    // Canvas fractions -> clip space
+    p = p + vec2<f32>(transform.position.x * 2.0, -transform.position.y * 2.0);
+
     var out: VertexOutput;
-    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
-    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
-    out.uv = uv;
+    out.position = vec4<f32>(p, 0.0, 1.0);
+    let quad_uv = vec2<f32>((corner.x + 1.0) * 0.5, (1.0 - corner.y) * 0.5);
+    out.uv = transform.uv_origin + quad_uv * transform.uv_size;
     return out;
 }
 
+// Must match ColorUniform in model.rs
+struct ColorConversion {
+    r: vec4<f32>, // Weights of (Y, U, V, 1), range expansion included
+    g: vec4<f32>,
+    b: vec4<f32>,
+    plane_layout: u32, // 0 = RGBA, 1 = I420 (Y, U, V), 2 = NV12 (Y, UV)
+};
+
 // Bindings (Must match Rust Layout)
-@group(0) @binding(0) var t_diffuse: texture_2d<f32>;
+@group(0) @binding(0) var t_plane0: texture_2d<f32>; // RGBA or Y
 @group(0) @binding(1) var s_diffuse: sampler;
+@group(0) @binding(2) var t_plane1: texture_2d<f32>; // U or UV (half size)
+@group(0) @binding(3) var t_plane2: texture_2d<f32>; // V (half size)
+@group(0) @binding(4) var<uniform> conversion: ColorConversion;
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.uv);
+    // Sample every plane first (textureSample needs uniform control flow)
+    let plane0 = textureSample(t_plane0, s_diffuse, in.uv);
+    let plane1 = textureSample(t_plane1, s_diffuse, in.uv);
+    let plane2 = textureSample(t_plane2, s_diffuse, in.uv);
+
+    var color = plane0;
+    if conversion.plane_layout != 0u {
+        var yuv = vec4<f32>(plane0.r, plane1.r, plane2.r, 1.0);
+        if conversion.plane_layout == 2u {
+            yuv = vec4<f32>(plane0.r, plane1.r, plane1.g, 1.0);
+        }
+        let rgb = vec3<f32>(dot(conversion.r, yuv), dot(conversion.g, yuv), dot(conversion.b, yuv));
+        color = vec4<f32>(clamp(rgb, vec3<f32>(0.0), vec3<f32>(1.0)), 1.0);
+    }
+    return vec4<f32>(color.rgb, color.a * transform.opacity);
 }